@@ -0,0 +1,427 @@
+// Commands:
+// Typed text-adventure commands for the player, after the Python prototype's
+// parser.py. Words are lowercased and their synonyms swapped in ("n" for
+// "north", "go" for "move"), "at" and "to" are dropped, and the verb decides
+// what follows. Moving, waiting, eating and talking become the same Tasks the
+// AI chooses; looking and checking the inventory are answered on the spot.
+// Objects are looked up by name among what the player can see, nearest
+// first, and directions are tiles on the grid with north up. Moves refuse
+// tiles something already stands on; going to someone stops beside them.
+
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::engine::actor::{Action, ActionParameters, Task, GREETING_RADIUS};
+use crate::engine::world::{Position, TileEntityMap};
+use crate::engine::Identity;
+
+pub const SIGHT_RADIUS: i64 = 8; // Tiles the player can see objects within
+const PLAYER_PRIORITY: u32 = u32::MAX; // Orders outrank anything else
+
+const VERBS: [&str; 10] = [
+    "move",
+    "look",
+    "take",
+    "inventory",
+    "wait",
+    "talk",
+    "open",
+    "close",
+    "eat",
+    "help",
+];
+const TRANSLATIONS: [(&str, &str); 12] = [
+    ("s", "south"),
+    ("n", "north"),
+    ("w", "west"),
+    ("e", "east"),
+    ("ne", "northeast"),
+    ("nw", "northwest"),
+    ("se", "southeast"),
+    ("sw", "southwest"),
+    ("go", "move"),
+    ("walk", "move"),
+    ("i", "inventory"),
+    ("l", "look"),
+];
+const PREPOSITIONS: [&str; 3] = ["at", "to", "with"];
+const DIRECTIONS: [(&str, i64, i64); 8] = [
+    ("north", 0, 1),
+    ("south", 0, -1),
+    ("east", 1, 0),
+    ("west", -1, 0),
+    ("northeast", 1, 1),
+    ("northwest", -1, 1),
+    ("southeast", 1, -1),
+    ("southwest", -1, -1),
+];
+
+// Something the player can see and refer to by name
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seen {
+    pub entity:   Entity,
+    pub name:     String,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Act(Task),
+    Reply(String), // Answered without acting
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    Empty,
+    UnknownVerb(String),
+    MissingObject(&'static str), // The verb
+    NotHere,
+    CannotGo(String),
+    CannotTake(String),
+    CannotOpen(String),
+    CannotClose(String),
+    TooFar(String),
+    InTheWay,
+}
+impl fmt::Display for CommandError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        match self {
+            CommandError::Empty => write!(f, "What should I do?"),
+            CommandError::UnknownVerb(word) => {
+                write!(f, "I don't know how to {}.", word)
+            }
+            CommandError::MissingObject(verb) => {
+                write!(f, "What should I {}?", verb)
+            }
+            CommandError::NotHere => write!(f, "I don't see that here."),
+            CommandError::CannotGo(direction) => {
+                write!(f, "I can't go {} from here.", direction)
+            }
+            CommandError::CannotTake(name) => {
+                write!(f, "I can't take {}.", name)
+            }
+            CommandError::CannotOpen(name) => {
+                write!(f, "{} cannot be opened.", name)
+            }
+            CommandError::CannotClose(name) => {
+                write!(f, "{} cannot be closed.", name)
+            }
+            CommandError::TooFar(name) => {
+                write!(f, "{} is too far away to talk to.", name)
+            }
+            CommandError::InTheWay => write!(f, "Something is in the way."),
+        }
+    }
+}
+impl std::error::Error for CommandError {}
+
+fn translate(word: &str) -> String {
+    // Swaps in the full word for a synonym, lowercased
+    let word = word.to_lowercase();
+    TRANSLATIONS
+        .iter()
+        .find(|(synonym, _)| *synonym == word)
+        .map_or(word, |(_, full)| (*full).to_owned())
+}
+
+fn list_to_natural(names: &[&str]) -> String {
+    match names {
+        [] => String::new(),
+        [only] => (*only).to_owned(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+fn find<'a>(
+    seen: &'a [Seen],
+    here: &Position,
+    words: &[String],
+) -> Result<&'a Seen, CommandError> {
+    // The nearest thing going by the full name or any word of it
+    let wanted = words.join(" ");
+    seen.iter()
+        .filter(|seen| {
+            let name = seen.name.to_lowercase();
+            name == wanted || name.split_whitespace().any(|word| word == wanted)
+        })
+        .min_by_key(|seen| here.distance_squared(&seen.position))
+        .ok_or(CommandError::NotHere)
+}
+
+fn go_to(
+    here: &Position,
+    location: Position,
+) -> Command {
+    // A GoTo to the tile underfoot would have no steps to finish it
+    if location == *here {
+        return Command::Reply("You are already here.".to_owned());
+    }
+    Command::Act(Task::new(
+        Action::GoTo,
+        ActionParameters::at(location),
+        PLAYER_PRIORITY,
+    ))
+}
+
+fn free(
+    to: &Position,
+    here: &Position,
+    occupied: impl Fn(&Position) -> bool,
+) -> bool {
+    // The player's own tile counts as free
+    to == here || !occupied(to)
+}
+
+pub fn parse(
+    input: &str,
+    here: Position,
+    seen: &[Seen],
+    walkable: impl Fn(&Position) -> bool,
+    occupied: impl Fn(&Position) -> bool,
+) -> Result<Command, CommandError> {
+    let words: Vec<String> = input
+        .split_whitespace()
+        .map(translate)
+        .filter(|word| !PREPOSITIONS.contains(&word.as_str()))
+        .collect();
+    let (verb, object) = match words.split_first() {
+        Some((verb, object)) => (verb.as_str(), object),
+        None => return Err(CommandError::Empty),
+    };
+    let verb = match VERBS.iter().find(|known| **known == verb) {
+        Some(verb) => *verb,
+        None => {
+            // A bare direction or name is a move, as in parser.py
+            let moved = parse(
+                &format!("move {}", input),
+                here,
+                seen,
+                walkable,
+                occupied,
+            );
+            return match moved {
+                Err(CommandError::NotHere) => {
+                    Err(CommandError::UnknownVerb(verb.to_owned()))
+                }
+                moved => moved,
+            };
+        }
+    };
+    if object.is_empty() {
+        return match verb {
+            "look" => {
+                let mut names: Vec<&str> =
+                    seen.iter().map(|seen| seen.name.as_str()).collect();
+                names.sort_unstable();
+                names.dedup();
+                let mut text =
+                    format!("I am standing at ({}, {}).", here.x, here.y);
+                if !names.is_empty() {
+                    text.push_str(&format!(
+                        " I can see {}.",
+                        list_to_natural(&names)
+                    ));
+                }
+                Ok(Command::Reply(text))
+            }
+            "inventory" => Ok(Command::Reply(
+                "I don't have anything at the moment.".to_owned(),
+            )),
+            "wait" => Ok(Command::Act(Task::new(
+                Action::Wait,
+                ActionParameters::default(),
+                PLAYER_PRIORITY,
+            ))),
+            "eat" => Ok(Command::Act(Task::new(
+                Action::Eat,
+                ActionParameters::default(),
+                PLAYER_PRIORITY,
+            ))),
+            "help" => Ok(Command::Reply(format!(
+                "I know how to {}.",
+                list_to_natural(&VERBS)
+            ))),
+            _ => Err(CommandError::MissingObject(verb)),
+        };
+    }
+    match verb {
+        "move" => {
+            if let Some((direction, x, y)) = DIRECTIONS
+                .iter()
+                .find(|(direction, ..)| *direction == object.join(" "))
+            {
+                let to = Position {
+                    x: here.x + x,
+                    y: here.y + y,
+                };
+                return if !walkable(&to) {
+                    Err(CommandError::CannotGo((*direction).to_owned()))
+                } else if !free(&to, &here, occupied) {
+                    Err(CommandError::InTheWay)
+                } else {
+                    Ok(go_to(&here, to))
+                };
+            }
+            // Coordinates, as "move to 12 30" or "move to 12,30"
+            let numbers: Vec<i64> = object
+                .iter()
+                .flat_map(|word| word.split(','))
+                .filter_map(|number| number.parse().ok())
+                .collect();
+            if let [x, y] = numbers[..] {
+                let to = Position { x, y };
+                return if !walkable(&to) {
+                    Err(CommandError::CannotGo(format!("to ({}, {})", x, y)))
+                } else if !free(&to, &here, occupied) {
+                    Err(CommandError::InTheWay)
+                } else {
+                    Ok(go_to(&here, to))
+                };
+            }
+            // Whoever is there stands on the tile, so stop next to them
+            let other = find(seen, &here, object)?;
+            other
+                .position
+                .get_range(1, 1)
+                .into_iter()
+                .filter(|tile| walkable(tile) && free(tile, &here, &occupied))
+                .min_by_key(|tile| here.distance_squared(tile))
+                .map(|tile| go_to(&here, tile))
+                .ok_or(CommandError::InTheWay)
+        }
+        "look" => find(seen, &here, object).map(|seen| {
+            Command::Reply(format!(
+                "I see {} at ({}, {}).",
+                seen.name, seen.position.x, seen.position.y
+            ))
+        }),
+        "talk" => {
+            let other = find(seen, &here, object)?;
+            if here.distance_squared(&other.position)
+                > GREETING_RADIUS * GREETING_RADIUS
+            {
+                return Err(CommandError::TooFar(other.name.clone()));
+            }
+            Ok(Command::Act(Task::new(
+                Action::Talk,
+                ActionParameters::targeting(other.entity),
+                PLAYER_PRIORITY,
+            )))
+        }
+        // Nothing can be picked up, opened or closed yet
+        "take" => find(seen, &here, object)
+            .and_then(|seen| Err(CommandError::CannotTake(seen.name.clone()))),
+        "open" => find(seen, &here, object)
+            .and_then(|seen| Err(CommandError::CannotOpen(seen.name.clone()))),
+        "close" => find(seen, &here, object)
+            .and_then(|seen| Err(CommandError::CannotClose(seen.name.clone()))),
+        // Eating, waiting and the rest take no object yet
+        _ => Err(CommandError::NotHere),
+    }
+}
+
+pub fn visible_to(
+    viewer: Entity,
+    here: &Position,
+    entity_map: &TileEntityMap,
+    identities: &Query<&Identity>,
+) -> Vec<Seen> {
+    // Named occupants within SIGHT_RADIUS, other than the viewer
+    let mut seen = Vec::new();
+    for position in here.get_range(SIGHT_RADIUS, SIGHT_RADIUS) {
+        let occupants = match entity_map.occupants(position.x, position.y) {
+            Some(occupants) => occupants,
+            None => continue,
+        };
+        let entities = occupants
+            .blocking
+            .iter()
+            .chain(occupants.non_blocking.iter())
+            .filter(|entity| **entity != viewer);
+        for entity in entities {
+            if let Ok(identity) = identities.get(*entity) {
+                seen.push(Seen {
+                    entity: *entity,
+                    name: identity.name.clone(),
+                    position,
+                });
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_like_the_text_adventure() {
+        let here = Position { x: 5, y: 5 };
+        let grumph = Entity::new(1);
+        let seen = vec![
+            Seen {
+                entity:   grumph,
+                name:     "Grumph Torgi".to_owned(),
+                position: Position { x: 6, y: 5 },
+            },
+            Seen {
+                entity:   Entity::new(2),
+                name:     "Lamp post".to_owned(),
+                position: Position { x: 3, y: 5 },
+            },
+        ];
+        let walkable = |position: &Position| position.x < 6;
+        let occupied = |position: &Position| {
+            [here, Position { x: 6, y: 5 }, Position { x: 4, y: 6 }]
+                .contains(position)
+        };
+        let parse = |input| parse(input, here, &seen, walkable, occupied);
+        assert_eq!(parse("go n"), Ok(go_to(&here, Position { x: 5, y: 6 })));
+        let already_here =
+            Ok(Command::Reply("You are already here.".to_owned()));
+        assert_eq!(parse("move to 5 5"), already_here);
+        assert_eq!(
+            parse("nw").unwrap_err().to_string(),
+            "Something is in the way."
+        );
+        // Already standing beside Grumph, whose own tile is taken
+        assert_eq!(parse("go to grumph"), already_here);
+        assert_eq!(parse("lamp"), Ok(go_to(&here, Position { x: 4, y: 5 })));
+        assert_eq!(parse("SW"), Ok(go_to(&here, Position { x: 4, y: 4 })));
+        assert_eq!(
+            parse("move to 3,9"),
+            Ok(go_to(&here, Position { x: 3, y: 9 }))
+        );
+        assert_eq!(parse("e"), Err(CommandError::CannotGo("east".to_owned())));
+        assert_eq!(
+            parse("talk to grumph"),
+            Ok(Command::Act(Task::new(
+                Action::Talk,
+                ActionParameters::targeting(grumph),
+                PLAYER_PRIORITY,
+            )))
+        );
+        assert_eq!(parse("look at the bench"), Err(CommandError::NotHere));
+        assert_eq!(
+            parse("take Grumph Torgi").unwrap_err().to_string(),
+            "I can't take Grumph Torgi."
+        );
+        assert_eq!(
+            parse("i"),
+            Ok(Command::Reply(
+                "I don't have anything at the moment.".to_owned()
+            ))
+        );
+        assert_eq!(parse("  "), Err(CommandError::Empty));
+        assert_eq!(
+            parse("dance").unwrap_err().to_string(),
+            "I don't know how to dance."
+        );
+        assert_eq!(parse("open"), Err(CommandError::MissingObject("open")));
+    }
+}
@@ -1,37 +1,137 @@
-use bevy::prelude::*;
+use std::collections::HashSet;
+
+use bevy::{ecs::query::QueryEntityError, prelude::*};
 
 use crate::engine::world;
 
+#[derive(Debug, Default)]
 pub struct Routine {
     tasks: Option<Vec<ScheduledTask>>,
 }
+impl Routine {
+    pub fn next(&self) -> Option<&ScheduledTask> {
+        self.tasks.as_ref().and_then(|tasks| tasks.first())
+    }
+    pub fn extend(
+        &mut self,
+        scheduled: impl IntoIterator<Item = ScheduledTask>,
+    ) {
+        // Keeps tasks in chronological order
+        let tasks = self.tasks.get_or_insert_with(Vec::new);
+        tasks.extend(scheduled);
+        tasks.sort_by_key(|scheduled| scheduled.time);
+    }
+    pub fn pop_next(&mut self) -> Option<ScheduledTask> {
+        match &mut self.tasks {
+            Some(tasks) if !tasks.is_empty() => Some(tasks.remove(0)),
+            _ => None,
+        }
+    }
+    pub fn discard_before(
+        &mut self,
+        time: world::time::GameTime,
+    ) {
+        if let Some(tasks) = &mut self.tasks {
+            tasks.retain(|scheduled| scheduled.time >= time);
+        }
+    }
+}
 
+#[derive(Debug)]
 pub struct ScheduledTask {
     task: Task,
     time: world::time::GameTime,
 }
+impl ScheduledTask {
+    pub fn new(
+        task: Task,
+        time: world::time::GameTime,
+    ) -> Self {
+        Self { task, time }
+    }
+    pub fn task(&self) -> Task { self.task }
+    pub fn time(&self) -> world::time::GameTime { self.time }
+}
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Task {
     action:     Action,
     parameters: ActionParameters,
     priority:   u32,
 }
+impl Task {
+    pub fn new(
+        action: Action,
+        parameters: ActionParameters,
+        priority: u32,
+    ) -> Self {
+        Self {
+            action,
+            parameters,
+            priority,
+        }
+    }
+    pub fn action(&self) -> Action { self.action }
+    pub fn parameters(&self) -> ActionParameters { self.parameters }
+    pub fn priority(&self) -> u32 { self.priority }
+}
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Action {
     Wait,
     Eat,
+    GoTo, // Travel to parameters.location
+    Talk, // Greet parameters.target
 }
 
-#[derive(Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct ActionParameters {
     location: Option<world::Position>,
     target:   Option<Entity>,
 }
+impl ActionParameters {
+    pub fn at(location: world::Position) -> Self {
+        Self {
+            location: Some(location),
+            ..Default::default()
+        }
+    }
+    pub fn targeting(target: Entity) -> Self {
+        Self {
+            target: Some(target),
+            ..Default::default()
+        }
+    }
+    pub fn location(&self) -> Option<world::Position> { self.location }
+    pub fn target(&self) -> Option<Entity> { self.target }
+}
 
 pub struct Intelligent; // Intelligent actor component
 
+const GREETING_RADIUS: i64 = 2; // Tiles
+
+// Things actors do that others could notice, for bubbles and logs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ActorEvent {
+    Began {
+        actor:  Entity,
+        action: Action,
+    },
+    Arrived {
+        actor: Entity,
+        at:    world::Position,
+    },
+    Greeted {
+        actor: Entity,
+        other: Entity,
+    },
+    NoPath {
+        actor: Entity,
+        to:    world::Position,
+    },
+}
+
+#[derive(Debug)]
 pub struct Status {
     // Used for keeping track of actor state, values are primarily used for
     // priority of subsequent action
@@ -39,6 +139,56 @@ pub struct Status {
     laziness: u32, /* Actor will prefer inaction over actions with lower
                     * priority than laziness */
 }
+impl Status {
+    pub fn new(laziness: u32) -> Self {
+        Self {
+            hunger: 0,
+            laziness,
+        }
+    }
+    pub fn hunger(&self) -> u32 { self.hunger }
+    pub fn set_hunger(
+        &mut self,
+        hunger: u32,
+    ) {
+        self.hunger = hunger;
+    }
+    pub fn laziness(&self) -> u32 { self.laziness }
+}
+
+pub fn next_task(
+    status: &Status,
+    routine: &mut Routine,
+    now: world::time::GameTime,
+) -> Task {
+    // A Routine task is due once its time has come; it is taken off the
+    // Routine when done, or when the actor is too lazy for it, but kept for
+    // later if hunger comes first
+    let idle = Task {
+        action:     Action::Wait,
+        parameters: ActionParameters::default(),
+        priority:   status.laziness,
+    };
+    let due = routine
+        .next()
+        .filter(|next| next.time <= now)
+        .map(ScheduledTask::task);
+    let task = match due {
+        Some(due) if due.priority > idle.priority => due,
+        _ => idle,
+    };
+    if status.hunger > task.priority {
+        return Task {
+            action:     Action::Eat,
+            parameters: ActionParameters::default(),
+            priority:   status.hunger,
+        };
+    }
+    if due.is_some() {
+        routine.pop_next();
+    }
+    task
+}
 
 pub struct ActorPlugin;
 
@@ -47,85 +197,199 @@ impl Plugin for ActorPlugin {
         &self,
         app: &mut AppBuilder,
     ) {
-        app.insert_resource(AnimalTimer(world::time::GameTime::from_stamp(
-            &world::time::Stamp {
-                day:    0,
-                hour:   6,
-                minute: 0,
-                second: 0,
-            },
-        )))
-        .add_system(pathfinding::plan_path.system().label("preparation"))
-        .add_system(
-            pathfinding::local_avoidance
-                .system()
-                .label("planning")
-                .after("preparation"),
-        )
-        .add_system(animal_processes.system().label("preparation"))
-        .add_system(choose_next_task.system().label("planning"))
-        .add_system(move_actor.system().label("action"));
+        app.add_event::<ActorEvent>()
+            .add_startup_system(schedule_animal_processes.system())
+            .add_system(pathfinding::plan_path.system().label("preparation"))
+            .add_system(
+                pathfinding::local_avoidance
+                    .system()
+                    .label("planning")
+                    .after("preparation"),
+            )
+            .add_system(
+                pathfinding::invalidate_paths
+                    .system()
+                    .label("planning")
+                    .after("preparation"),
+            )
+            .add_system(animal_processes.system().label("preparation"))
+            .add_system(
+                schedule::expand_recurring_schedules
+                    .system()
+                    .label("preparation"),
+            )
+            .add_system(choose_next_task.system().label("planning"))
+            .add_system(begin_travel.system().label("planning"))
+            .add_system(begin_talking.system().label("planning"))
+            .add_system(announce_tasks.system().label("planning"))
+            .add_event::<player::PlayerOrder>()
+            .add_system(player::direct_player.system().label("planning"))
+            .add_system(schedule_actor_steps.system().label("preparation"))
+            .add_system(move_actor.system().label("action"))
+            .init_resource::<narrative::NarrativeLog>()
+            .add_system(
+                narrative::record_actor_events.system().after("action"),
+            );
     }
 }
 
 fn choose_next_task(
     mut commands: Commands,
     mut query: Query<
-        (Entity, &Status, &Routine),
-        (With<Intelligent>, Without<Task>),
+        (Entity, &Status, &mut Routine, Option<&Task>),
+        With<Intelligent>,
     >,
     time: Res<world::time::GameTime>,
 ) {
-    for (entity, status, routine) in query.iter_mut() {
-        let mut curtask = Task {
-            action:     Action::Wait,
-            parameters: ActionParameters {
-                ..Default::default()
-            },
-            priority:   status.laziness,
-        };
-        // TODO: Change priority calculation for scheduled events to incorporate
-        // eta
-        match &routine.tasks {
-            Some(tasks) => {
-                let priority = time.how_soon(tasks[0].time);
-                if priority > curtask.priority {
-                    curtask = tasks[0].task;
-                }
-            }
-            None => todo!(),
+    // Waiting is only idling, so a waiting actor is free to choose again
+    for (entity, status, mut routine, current) in query.iter_mut() {
+        if current.map_or(false, |task| task.action != Action::Wait) {
+            continue;
         }
-        if status.hunger > curtask.priority {
-            curtask = Task {
-                action:     Action::Eat,
-                parameters: ActionParameters::default(),
-                priority:   status.hunger,
+        let task = next_task(status, &mut routine, *time);
+        // Removed first, as only a newly Added Task is begun and announced
+        match current {
+            Some(current) if *current == task => (),
+            Some(_) => {
+                commands.entity(entity).remove::<Task>().insert(task);
+            }
+            None => {
+                commands.entity(entity).insert(task);
             }
         }
-        commands.entity(entity).insert(curtask);
+    }
+}
+
+fn begin_travel(
+    // Tasks that name a location send the actor there
+    mut commands: Commands,
+    query: Query<(Entity, &Task), Added<Task>>,
+) {
+    for (entity, task) in query.iter() {
+        if let (Action::GoTo, Some(location)) =
+            (task.action, task.parameters.location)
+        {
+            commands.entity(entity).insert(world::Destination(location));
+        }
+    }
+}
+
+fn begin_talking(
+    // Tasks that name someone to talk to greet them
+    query: Query<(Entity, &Task), Added<Task>>,
+    mut events: EventWriter<ActorEvent>,
+) {
+    for (entity, task) in query.iter() {
+        if let (Action::Talk, Some(other)) =
+            (task.action, task.parameters.target)
+        {
+            events.send(ActorEvent::Greeted {
+                actor: entity,
+                other,
+            });
+        }
     }
 }
 
 struct Animal; // Component marker for animals (including humans)
 
-struct AnimalTimer(world::time::GameTime);
+const ANIMAL_PROCESSES: &str = "animal_processes";
+
+fn announce_tasks(
+    query: Query<(Entity, &Task), Added<Task>>,
+    mut events: EventWriter<ActorEvent>,
+) {
+    for (entity, task) in query.iter() {
+        events.send(ActorEvent::Began {
+            actor:  entity,
+            action: task.action,
+        });
+    }
+}
+
+fn schedule_actor_steps(
+    mut commands: Commands,
+    game_time: Res<world::time::GameTime>,
+    mut scheduler: ResMut<world::scheduler::Scheduler>,
+    actors: Query<Entity, (With<LastStep>, Without<StepSchedule>)>,
+) {
+    // Despawned actors are dropped from the Scheduler by move_actor
+    for entity in actors.iter() {
+        let id = scheduler.every(
+            *game_time,
+            STEP_INTERVAL,
+            ACTOR_STEP,
+            Some(entity),
+        );
+        commands.entity(entity).insert(StepSchedule(id));
+    }
+}
+
+fn schedule_animal_processes(
+    game_time: Res<world::time::GameTime>,
+    mut scheduler: ResMut<world::scheduler::Scheduler>,
+) {
+    scheduler.every(
+        *game_time,
+        world::time::GameDuration::from_minutes(1),
+        ANIMAL_PROCESSES,
+        None,
+    );
+}
 
 fn animal_processes(
     // Updates animal-inherent statuses; hunger, thirst, etc.
     mut query: Query<&mut Status, With<Animal>>,
-    game_time: Res<world::time::GameTime>,
-    mut timer: ResMut<AnimalTimer>,
+    mut events: EventReader<world::scheduler::ScheduledEvent>,
 ) {
-    if timer.0 <= *game_time {
+    for _ in events.iter().filter(|event| event.name == ANIMAL_PROCESSES) {
         for mut status in query.iter_mut() {
             status.hunger += 1;
         }
-        *timer = AnimalTimer(game_time.copy_and_tick(60));
     }
 }
 
+#[derive(Debug)]
 pub struct Orientation(pub Direction);
 
+// The tile an actor last stepped from and when, down to the SubSecond;
+// renderers slide the sprite from there to its Position over duration, the
+// game seconds the step before took
+#[derive(Debug, Copy, Clone)]
+pub struct LastStep {
+    pub from:       world::Position,
+    pub at:         world::time::GameTime,
+    pub sub_second: f32,
+    pub duration:   f32,
+}
+impl LastStep {
+    pub fn elapsed(
+        &self,
+        now: world::time::GameTime,
+        sub_second: f32,
+    ) -> f32 {
+        // Game seconds since the step, fractions included
+        now.saturating_duration_since(self.at).as_seconds() as f32 + sub_second
+            - self.sub_second
+    }
+}
+
+const STEP_SECONDS: f32 = 1.0; // The longest a step is drawn over
+
+// Every actor steps on its own recurring event on the Scheduler
+pub const ACTOR_STEP: &str = "actor_step";
+pub const STEP_INTERVAL: world::time::GameDuration =
+    world::time::GameDuration::from_seconds(1);
+
+#[derive(Debug)]
+pub struct StepSchedule(pub world::scheduler::ScheduleId);
+
+// Steps in a row the occupancy map has refused an actor
+#[derive(Debug)]
+pub struct StepRetries(u32);
+
+const MAX_STEP_RETRIES: u32 = 3; // Before the Path is given up
+
 #[derive(Debug, Copy, Clone, PartialEq, Hash, Eq)]
 pub enum Direction {
     Up,
@@ -138,73 +402,156 @@ pub enum Direction {
     Right,
 }
 
+pub mod command;
+pub mod narrative;
 mod pathfinding;
+pub mod player;
+pub mod schedule;
+
+pub use pathfinding::{trace_path, Path, PathCollapsed, PathFailed};
 
 pub fn move_actor(
     mut entity_map: ResMut<world::TileEntityMap>,
+    mut spatial_index: ResMut<world::spatial::SpatialIndex>,
+    mut traffic: ResMut<world::traffic::TrafficMap>,
+    mut scheduler: ResMut<world::scheduler::Scheduler>,
     game_time: Res<world::time::GameTime>,
+    sub_second: Res<world::time::SubSecond>,
     mut commands: Commands,
+    mut due: EventReader<world::scheduler::ScheduledEvent>,
+    mut events: EventWriter<ActorEvent>,
     mut query: Query<(
-        Entity,
-        &mut world::time::GameTime,
         &mut world::Position,
+        &world::Occupancy,
         &mut Orientation,
-        &world::Destination,
+        &mut LastStep,
+        Option<&world::Destination>, // None for the player's single steps
+        Option<&StepRetries>,
+        Option<&Task>,
         &mut pathfinding::Path,
     )>,
 ) {
-    for (
-        entity,
-        mut timer,
-        mut position,
-        mut orientation,
-        destination,
-        mut path,
-    ) in &mut query.iter_mut()
-    {
+    // Actors only move on their ACTOR_STEP events; one that fell several
+    // steps behind over a long frame takes a single step
+    let mut stepped = HashSet::new();
+    for event in due.iter().filter(|event| event.name == ACTOR_STEP) {
+        let entity = match event.target {
+            Some(entity) if stepped.insert(entity) => entity,
+            _ => continue,
+        };
+        let (
+            mut position,
+            occupancy,
+            mut orientation,
+            mut last_step,
+            destination,
+            retries,
+            task,
+            mut path,
+        ) = match query.get_mut(entity) {
+            Ok(actor) => actor,
+            Err(QueryEntityError::NoSuchEntity) => {
+                scheduler.cancel(event.id);
+                continue;
+            }
+            Err(_) => continue, // Standing still, without a Path
+        };
         if path.0.is_empty() {
             commands.entity(entity).remove::<pathfinding::Path>();
-        } else if *timer <= *game_time {
+        } else {
             let next_step = path.0.remove(0); // path.0[0]; //
-            let next_direction = next_step - *position;
-            match next_direction {
-                world::Position { x: 1, .. } => {
-                    *orientation = Orientation(Direction::Up);
-                }
-                world::Position { x: -1, .. } => {
-                    *orientation = Orientation(Direction::Down);
-                }
-                world::Position { y: 1, .. } => {
-                    *orientation = Orientation(Direction::Right);
+
+            // Move the actor; the occupancy map refuses a tile that already
+            // holds a blocking occupant, in which case a step towards a
+            // Destination is retried on the next turns, up to
+            // MAX_STEP_RETRIES times, and the player's single step is dropped
+            match entity_map.place(entity, next_step, *occupancy) {
+                Ok(()) => {
+                    let next_direction = next_step - *position;
+                    match next_direction {
+                        world::Position { x: 1, .. } => {
+                            *orientation = Orientation(Direction::Up);
+                        }
+                        world::Position { x: -1, .. } => {
+                            *orientation = Orientation(Direction::Down);
+                        }
+                        world::Position { y: 1, .. } => {
+                            *orientation = Orientation(Direction::Right);
+                        }
+                        world::Position { y: -1, .. } => {
+                            *orientation = Orientation(Direction::Left);
+                        }
+                        _ => (),
+                    }
+                    let duration = last_step
+                        .elapsed(*game_time, sub_second.0)
+                        .min(STEP_SECONDS);
+                    *last_step = LastStep {
+                        from: *position,
+                        at: *game_time,
+                        sub_second: sub_second.0,
+                        duration,
+                    };
+                    *position = next_step;
+                    spatial_index.insert(entity, next_step);
+                    traffic.record(&next_step, *game_time);
+                    if retries.is_some() {
+                        commands.entity(entity).remove::<StepRetries>();
+                    }
                 }
-                world::Position { y: -1, .. } => {
-                    *orientation = Orientation(Direction::Left);
+                Err(error) => {
+                    // Stand still rather than slide back towards the last tile
+                    *last_step = LastStep {
+                        from:       *position,
+                        at:         *game_time,
+                        sub_second: sub_second.0,
+                        duration:   0.0,
+                    };
+                    // An occupant may move on, the edge of the map never will;
+                    // without a Path plan_path and local_avoidance plan anew
+                    let failures = retries.map_or(1, |retries| retries.0 + 1);
+                    if destination.is_some()
+                        && error != world::OccupancyError::OutOfBounds
+                        && failures < MAX_STEP_RETRIES
+                    {
+                        path.0.insert(0, next_step);
+                        commands.entity(entity).insert(StepRetries(failures));
+                    } else {
+                        commands
+                            .entity(entity)
+                            .remove::<pathfinding::Path>()
+                            .remove::<StepRetries>();
+                    }
                 }
-                _ => (),
             }
-            // Destructure for convenience
-            let old_x = position.x;
-            let old_y = position.y;
-
-            let new_x = next_step.x;
-            let new_y = next_step.y;
-
-            // Mark previous tile as unoccupied
-            entity_map.set(old_x, old_y, None);
-            // Move the actor
-            *position = next_step;
-            // Mark next tile as occupied
-            entity_map.set(new_x, new_y, Some(entity));
-            // Set time of next action
-            *timer = game_time.copy_and_tick(1);
-        } else {
-            *timer = game_time.copy_and_tick(0);
         }
-        if *destination == *position {
+        if destination.map_or(false, |destination| *destination == *position) {
+            events.send(ActorEvent::Arrived {
+                actor: entity,
+                at:    *position,
+            });
+            // Say hello to whoever is standing closest
+            if let Some((other, _)) = spatial_index
+                .nearest(&position, 1, |other| other != entity)
+                .into_iter()
+                .find(|(_, near)| {
+                    position.distance_squared(near)
+                        <= GREETING_RADIUS * GREETING_RADIUS
+                })
+            {
+                events.send(ActorEvent::Greeted {
+                    actor: entity,
+                    other,
+                });
+            }
             commands
                 .entity(entity)
                 .remove::<world::Destination>()
                 .remove::<pathfinding::Path>();
+            // A GoTo is done once there, which frees the actor for the next
+            if task.map_or(false, |task| task.action == Action::GoTo) {
+                commands.entity(entity).remove::<Task>();
+            }
         }
     }
 }
@@ -0,0 +1,297 @@
+// Narrative log:
+// Records the ActorEvents as structured entries (actor, verb, object,
+// location, success or the reason it failed) and tells them as English
+// sentences from an observer's point of view, following the Python
+// prototype's output.py: the observer is "I", an actor named in the previous
+// sentence becomes "they", actors with a specific Identity go by their name
+// and everyone else is "someone". Waiting is not worth telling.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::engine::actor::{Action, ActorEvent};
+use crate::engine::world::{time::GameTime, Position};
+
+const CAPACITY: usize = 500; // Entries kept, the oldest are dropped
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Verb {
+    Eat,
+    SetOff,
+    Arrive,
+    Greet,
+}
+impl Verb {
+    fn phrase(
+        self,
+        third_person: bool,
+    ) -> &'static str {
+        match (self, third_person) {
+            (Verb::Eat, false) => "start to eat",
+            (Verb::Eat, true) => "starts to eat",
+            (Verb::SetOff, false) => "try to reach",
+            (Verb::SetOff, true) => "tries to reach",
+            (Verb::Arrive, false) => "arrive at",
+            (Verb::Arrive, true) => "arrives at",
+            (Verb::Greet, false) => "greet",
+            (Verb::Greet, true) => "greets",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub time:     GameTime,
+    pub actor:    Entity,
+    pub verb:     Verb,
+    pub object:   Option<Entity>,
+    pub place:    Option<Position>, // Where the verb leads, if anywhere
+    pub location: Position,         // Where the actor stood
+    pub outcome:  Result<(), &'static str>, // The reason on failure
+}
+impl LogEntry {
+    pub fn from_event(
+        event: &ActorEvent,
+        time: GameTime,
+        location: Position,
+    ) -> Option<Self> {
+        let (actor, verb, object, place, outcome) = match *event {
+            ActorEvent::Began {
+                actor,
+                action: Action::Eat,
+            } => (actor, Verb::Eat, None, None, Ok(())),
+            // Talking is told by the Greeted event it leads to
+            ActorEvent::Began {
+                action: Action::Wait | Action::Talk,
+                ..
+            } => return None,
+            // The destination is only known once planning succeeds or fails
+            ActorEvent::Began {
+                action: Action::GoTo,
+                ..
+            } => return None,
+            ActorEvent::Arrived { actor, at } => {
+                (actor, Verb::Arrive, None, Some(at), Ok(()))
+            }
+            ActorEvent::Greeted { actor, other } => {
+                (actor, Verb::Greet, Some(other), None, Ok(()))
+            }
+            ActorEvent::NoPath { actor, to } => (
+                actor,
+                Verb::SetOff,
+                None,
+                Some(to),
+                Err("there is no way there"),
+            ),
+        };
+        Some(Self {
+            time,
+            actor,
+            verb,
+            object,
+            place,
+            location,
+            outcome,
+        })
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogFilter {
+    All,
+    Actor(Entity),       // Entries the actor takes part in
+    Zone(Position, i64), // Entries within a radius of a tile
+}
+impl Default for LogFilter {
+    fn default() -> Self { LogFilter::All }
+}
+impl LogFilter {
+    pub fn matches(
+        &self,
+        entry: &LogEntry,
+    ) -> bool {
+        match *self {
+            LogFilter::All => true,
+            LogFilter::Actor(entity) => {
+                entry.actor == entity || entry.object == Some(entity)
+            }
+            LogFilter::Zone(centre, radius) => {
+                centre.distance_squared(&entry.location) <= radius * radius
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct NarrativeLog {
+    entries: VecDeque<LogEntry>,
+}
+impl NarrativeLog {
+    pub fn push(
+        &mut self,
+        entry: LogEntry,
+    ) {
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+    pub fn filtered<'a>(
+        &'a self,
+        filter: &'a LogFilter,
+    ) -> impl Iterator<Item = &'a LogEntry> + 'a {
+        // Oldest first
+        self.entries
+            .iter()
+            .filter(move |entry| filter.matches(entry))
+    }
+}
+
+pub struct Narrator {
+    pub observer: Option<Entity>, // Told as "I", None for an outside view
+    last_subject: Option<Entity>,
+}
+impl Narrator {
+    pub fn new(observer: Option<Entity>) -> Self {
+        Self {
+            observer,
+            last_subject: None,
+        }
+    }
+    fn noun(
+        &self,
+        entity: Entity,
+        name_of: &impl Fn(Entity) -> Option<String>,
+        as_object: bool,
+    ) -> (String, bool) {
+        // The word for an entity and whether it takes third person verbs
+        if self.observer == Some(entity) {
+            let word = if as_object { "me" } else { "I" };
+            return (word.to_owned(), false);
+        }
+        if !as_object && self.last_subject == Some(entity) {
+            return ("they".to_owned(), false);
+        }
+        match name_of(entity) {
+            Some(name) => (name, true),
+            None => ("someone".to_owned(), true),
+        }
+    }
+    pub fn tell(
+        &mut self,
+        entry: &LogEntry,
+        name_of: impl Fn(Entity) -> Option<String>,
+    ) -> String {
+        let (subject, third_person) = self.noun(entry.actor, &name_of, false);
+        let mut sentence =
+            format!("{} {}", subject, entry.verb.phrase(third_person));
+        if let Some(object) = entry.object {
+            sentence.push(' ');
+            sentence.push_str(&self.noun(object, &name_of, true).0);
+        }
+        if let Some(place) = entry.place {
+            sentence.push_str(&format!(" ({}, {})", place.x, place.y));
+        }
+        if let Err(reason) = entry.outcome {
+            sentence.push_str(", but ");
+            sentence.push_str(reason);
+        }
+        sentence.push('.');
+        self.last_subject = Some(entry.actor);
+        let mut characters = sentence.chars();
+        match characters.next() {
+            Some(first) => first.to_uppercase().chain(characters).collect(),
+            None => sentence,
+        }
+    }
+}
+
+pub fn record_actor_events(
+    game_time: Res<GameTime>,
+    mut events: EventReader<ActorEvent>,
+    mut log: ResMut<NarrativeLog>,
+    positions: Query<&Position>,
+) {
+    for event in events.iter() {
+        let actor = match *event {
+            ActorEvent::Began { actor, .. }
+            | ActorEvent::Arrived { actor, .. }
+            | ActorEvent::Greeted { actor, .. }
+            | ActorEvent::NoPath { actor, .. } => actor,
+        };
+        let location = match positions.get(actor) {
+            Ok(position) => *position,
+            Err(_) => continue,
+        };
+        if let Some(entry) = LogEntry::from_event(event, *game_time, location) {
+            log.push(entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::time::Stamp;
+
+    #[test]
+    fn tells_from_the_observer_point_of_view() {
+        let (grumph, stranger, me) =
+            (Entity::new(1), Entity::new(2), Entity::new(3));
+        let name_of = |entity| {
+            if entity == grumph {
+                Some("Grumph".to_owned())
+            } else {
+                None
+            }
+        };
+        let time = GameTime::from_stamp(&Stamp {
+            day:    0,
+            hour:   6,
+            minute: 0,
+            second: 0,
+        });
+        let here = Position { x: 1, y: 1 };
+        let entry = |event| LogEntry::from_event(&event, time, here).unwrap();
+        let mut narrator = Narrator::new(Some(me));
+        let lines: Vec<String> = [
+            entry(ActorEvent::Arrived {
+                actor: grumph,
+                at:    Position { x: 4, y: 2 },
+            }),
+            entry(ActorEvent::Greeted {
+                actor: grumph,
+                other: me,
+            }),
+            entry(ActorEvent::Greeted {
+                actor: me,
+                other: stranger,
+            }),
+            entry(ActorEvent::NoPath {
+                actor: stranger,
+                to:    here,
+            }),
+        ]
+        .iter()
+        .map(|entry| narrator.tell(entry, name_of))
+        .collect();
+        assert_eq!(lines, vec![
+            "Grumph arrives at (4, 2).",
+            "They greet me.",
+            "I greet someone.",
+            "Someone tries to reach (1, 1), but there is no way there.",
+        ]);
+        let waiting = ActorEvent::Began {
+            actor:  me,
+            action: Action::Wait,
+        };
+        assert_eq!(LogEntry::from_event(&waiting, time, here), None);
+        assert!(LogFilter::Actor(me).matches(&entry(ActorEvent::Greeted {
+            actor: grumph,
+            other: me,
+        })));
+    }
+}
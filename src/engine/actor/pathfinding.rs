@@ -8,21 +8,28 @@
 //
 
 use std::cmp::min;
+use std::collections::HashSet;
 
 use bevy::prelude::*;
 use pathfinding::prelude::{absdiff, astar};
 
-use crate::engine::world::{Destination, Position, TileEntityMap, TileWeightMap};
+use crate::engine::actor::ActorEvent;
+use crate::engine::world::{edit::TilesChanged, spatial::SpatialIndex,
+                           Destination, Position, TileEntityMap, TileWeightMap};
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Path(pub Vec<Position>);
 impl Path {}
 
+pub struct PathFailed; // get_path found no route to the Destination
+pub struct PathCollapsed; // local_avoidance left the actor on its own tile
+
 pub fn local_avoidance(
-    // mut commands: Commands,
+    mut commands: Commands,
     entity_map: Res<TileEntityMap>,
     weight_map: Res<TileWeightMap>,
-    mut query: Query<(/* Entity, */ &Position, &mut Path)>,
+    spatial_index: Res<SpatialIndex>,
+    mut query: Query<(Entity, &Position, &mut Path)>,
 ) {
     // Path Wars: Episode IV
     // It is a period of civil war.
@@ -34,15 +41,25 @@ pub fn local_avoidance(
     // Powerful enough to destroy an entire planet, its completion spells
     // certain doom for the champions of freedom.
 
-    for (/* entity, */ position, mut path) in query.iter_mut() {
-        let mut nearby_entities = Vec::new();
-        for near_position in position.get_range(1, 1) {
-            if let Some(entity) =
-                entity_map.get(near_position.x, near_position.y)
-            {
-                nearby_entities.push(entity)
-            }
-        }
+    for (entity, position, mut path) in query.iter_mut() {
+        let nearby_entities = spatial_index.within_rect(
+            &Position {
+                x: position.x - 1,
+                y: position.y - 1,
+            },
+            &Position {
+                x: position.x + 1,
+                y: position.y + 1,
+            },
+            |other| other != entity,
+        );
+        // Only blocking occupants need to be walked around
+        let nearby_entities: Vec<_> = nearby_entities
+            .into_iter()
+            .filter(|(other, near)| {
+                entity_map.get(near.x, near.y) == Some(*other)
+            })
+            .collect();
         if !nearby_entities.is_empty() {
             // TODO: Cleanup during rewrite
             let mut index = 2;
@@ -61,25 +78,70 @@ pub fn local_avoidance(
             );
             path.0 = match local_path {
                 Some(mut p) => {
+                    commands.entity(entity).remove::<PathCollapsed>();
                     p.extend(path.0[index + 1..].iter().cloned());
                     p
                 }
-                None => vec![*position],
+                None => {
+                    commands.entity(entity).insert(PathCollapsed);
+                    vec![*position]
+                }
             }
         }
     }
 }
 
+pub fn invalidate_paths(
+    // Drops any path that crosses an edited tile; plan_path then replans
+    // from the actor's current position on the next pass.
+    mut commands: Commands,
+    mut changes: EventReader<TilesChanged>,
+    query: Query<(Entity, &Path)>,
+) {
+    let changed: HashSet<Position> = changes
+        .iter()
+        .flat_map(|TilesChanged(positions)| positions.iter().copied())
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    for (entity, path) in query.iter() {
+        if path.0.iter().any(|step| changed.contains(step)) {
+            commands.entity(entity).remove::<Path>();
+        }
+    }
+}
+
 pub fn plan_path(
     mut commands: Commands,
-    query: Query<(Entity, &Position, &Destination), Without<Path>>,
+    query: Query<
+        (Entity, &Position, &Destination, Option<&PathFailed>),
+        Without<Path>,
+    >,
     weight_map: Res<TileWeightMap>,
+    mut events: EventWriter<ActorEvent>,
 ) {
-    for (entity, position, destination) in query.iter() {
+    for (entity, position, destination, failed) in query.iter() {
         let plan = get_path(position, &destination.0, &weight_map);
-        if let Some(p) = plan {
-            if !p.is_empty() {
-                commands.entity(entity).insert(Path(p));
+        match plan {
+            Some(p) => {
+                commands
+                    .entity(entity)
+                    .remove::<PathFailed>()
+                    .remove::<PathCollapsed>();
+                if !p.is_empty() {
+                    commands.entity(entity).insert(Path(p));
+                }
+            }
+            None => {
+                // Replanned every frame, reported once
+                if failed.is_none() {
+                    events.send(ActorEvent::NoPath {
+                        actor: entity,
+                        to:    destination.0,
+                    });
+                }
+                commands.entity(entity).insert(PathFailed);
             }
         }
     }
@@ -89,11 +151,34 @@ pub fn get_path(
     position: &Position,
     destination: &Position,
     weight_map: &Res<TileWeightMap>,
+) -> Option<Vec<Position>> {
+    search(position, destination, weight_map, |_| ())
+}
+
+pub fn trace_path(
+    position: &Position,
+    destination: &Position,
+    weight_map: &Res<TileWeightMap>,
+    expanded: &mut Vec<Position>,
+) -> Option<Vec<Position>> {
+    // get_path that also records every node aStar expanded, in order; only
+    // the debug overlay pays for the recording
+    search(position, destination, weight_map, |p| expanded.push(*p))
+}
+
+fn search(
+    position: &Position,
+    destination: &Position,
+    weight_map: &Res<TileWeightMap>,
+    mut on_expand: impl FnMut(&Position),
 ) -> Option<Vec<Position>> {
     let mut path = Vec::new();
     let plan = astar(
         position,
-        |p| neighbors_with_weights(p, weight_map),
+        |p| {
+            on_expand(p);
+            neighbors_with_weights(p, weight_map)
+        },
         |p| diagonal_distance(p, destination),
         |p| {
             *p == *destination
@@ -0,0 +1,109 @@
+// Player:
+// The actor the user controls, as the Python prototype's User was. Orders
+// arrive as PlayerOrder events from whatever front end reads the input, and
+// are turned into Paths so that move_actor walks the player by the same rules
+// as everyone else, never onto an occupied tile. A single step refuses
+// obstacles and occupied tiles up front and replaces a step not yet taken;
+// move_actor drops it if the tile is taken by then. Walking to a tile plans
+// the whole route with get_path.
+
+use bevy::prelude::*;
+
+use crate::engine::actor::pathfinding::{get_path, Path, PathFailed};
+use crate::engine::actor::{ActorEvent, StepRetries};
+use crate::engine::world::{Destination, Position, TileEntityMap, TileWeightMap};
+
+pub struct Player; // Marks the actor under direct control
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PlayerOrder {
+    Step(Position), // Offset to a neighbouring tile
+    WalkTo(Position),
+}
+
+pub fn step_target(
+    position: &Position,
+    offset: Position,
+    weight_map: &TileWeightMap,
+    entity_map: &TileEntityMap,
+) -> Option<Position> {
+    // The tile a single step lands on, if it can be walked onto now
+    let to = Position {
+        x: position.x + offset.x.signum(),
+        y: position.y + offset.y.signum(),
+    };
+    if to == *position
+        || weight_map.get(to.x, to.y) == i64::MAX
+        || entity_map.is_blocked(to.x, to.y)
+    {
+        return None;
+    }
+    Some(to)
+}
+
+pub fn direct_player(
+    mut commands: Commands,
+    mut orders: EventReader<PlayerOrder>,
+    mut events: EventWriter<ActorEvent>,
+    weight_map: Res<TileWeightMap>,
+    entity_map: Res<TileEntityMap>,
+    players: Query<(Entity, &Position), With<Player>>,
+) {
+    for order in orders.iter() {
+        for (entity, position) in players.iter() {
+            match *order {
+                PlayerOrder::Step(offset) => {
+                    // Stepping cancels a walk, or a step not yet taken
+                    if let Some(to) =
+                        step_target(position, offset, &weight_map, &entity_map)
+                    {
+                        commands
+                            .entity(entity)
+                            .remove::<Destination>()
+                            .remove::<StepRetries>()
+                            .insert(Path(vec![to]));
+                    }
+                }
+                PlayerOrder::WalkTo(to) => {
+                    match get_path(position, &to, &weight_map) {
+                        Some(steps) => {
+                            commands
+                                .entity(entity)
+                                .remove::<PathFailed>()
+                                .insert(Destination(to))
+                                .insert(Path(steps));
+                        }
+                        None => events
+                            .send(ActorEvent::NoPath { actor: entity, to }),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::Occupancy;
+
+    #[test]
+    fn steps_stay_off_obstacles_and_occupants() {
+        let mut weight_map = TileWeightMap::new(3, 3);
+        weight_map.set(2, 1, i64::MAX);
+        let mut entity_map = TileEntityMap::new(3, 3);
+        let other = Entity::new(7);
+        entity_map
+            .place(other, Position { x: 1, y: 2 }, Occupancy::Blocking)
+            .unwrap();
+        let here = Position { x: 1, y: 1 };
+        let step = |x, y| {
+            step_target(&here, Position { x, y }, &weight_map, &entity_map)
+        };
+        assert_eq!(step(-3, 0), Some(Position { x: 0, y: 1 }));
+        assert_eq!(step(1, -1), Some(Position { x: 2, y: 0 }));
+        assert_eq!(step(1, 0), None);
+        assert_eq!(step(0, 1), None);
+        assert_eq!(step(0, 0), None);
+    }
+}
@@ -0,0 +1,210 @@
+// Recurring schedules:
+// Routines only hold ScheduledTasks for absolute times. Actors with a
+// RecurringSchedule describe their week instead ("every weekday at 09:00 go to
+// work", "Sundays at noon visit the park"); at the start of every game day the
+// rules that apply to that day are expanded into the actor's Routine, where
+// choose_next_task takes each one off once it is due. A rule fires again on
+// the next day it applies to.
+
+use bevy::prelude::*;
+
+use crate::engine::actor::{Routine, ScheduledTask, Task};
+use crate::engine::world::{calendar::{Calendar, Date, Season, Weekday},
+                           time::{GameTime, Stamp}};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaySelector {
+    Everyday,
+    Weekdays,
+    Weekends,
+    On(Vec<Weekday>),
+    During(Season),
+}
+impl DaySelector {
+    pub fn matches(
+        &self,
+        date: &Date,
+    ) -> bool {
+        match self {
+            DaySelector::Everyday => true,
+            DaySelector::Weekdays => !date.weekday.is_weekend(),
+            DaySelector::Weekends => date.weekday.is_weekend(),
+            DaySelector::On(days) => days.contains(&date.weekday),
+            DaySelector::During(season) => date.season == *season,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ScheduleRule {
+    pub days:   DaySelector,
+    pub hour:   u32,
+    pub minute: u32,
+    pub task:   Task,
+}
+
+#[derive(Clone, Default)]
+pub struct RecurringSchedule(pub Vec<ScheduleRule>);
+
+pub fn tasks_for_day(
+    rules: &[ScheduleRule],
+    calendar: &Calendar,
+    day: u32,
+) -> Vec<ScheduledTask> {
+    let date = calendar.date_of_day(day);
+    let mut tasks: Vec<ScheduledTask> = rules
+        .iter()
+        .filter(|rule| rule.days.matches(&date))
+        .map(|rule| {
+            ScheduledTask::new(
+                rule.task,
+                GameTime::from_stamp(&Stamp {
+                    day,
+                    hour: rule.hour,
+                    minute: rule.minute,
+                    second: 0,
+                }),
+            )
+        })
+        .collect();
+    tasks.sort_by_key(|scheduled| scheduled.time());
+    tasks
+}
+
+pub fn expand_day(
+    routine: &mut Routine,
+    rules: &[ScheduleRule],
+    calendar: &Calendar,
+    now: GameTime,
+) {
+    // Tasks still left over, or already past for a schedule added late in the
+    // day, are dropped
+    routine.extend(tasks_for_day(rules, calendar, now.get_stamp().day));
+    routine.discard_before(now);
+}
+
+pub fn expand_recurring_schedules(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    calendar: Res<Calendar>,
+    mut last_day: Local<Option<u32>>,
+    mut query: Query<(Entity, &RecurringSchedule, Option<&mut Routine>)>,
+    new_schedules: Query<Entity, Added<RecurringSchedule>>,
+) {
+    let today = game_time.get_stamp().day;
+    let new_day = *last_day != Some(today);
+    *last_day = Some(today);
+    for (entity, schedule, routine) in query.iter_mut() {
+        if !new_day && new_schedules.get(entity).is_err() {
+            continue;
+        }
+        match routine {
+            Some(mut routine) => {
+                expand_day(&mut routine, &schedule.0, &calendar, *game_time);
+            }
+            None => {
+                let mut routine = Routine::default();
+                expand_day(&mut routine, &schedule.0, &calendar, *game_time);
+                commands.entity(entity).insert(routine);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::actor::{next_task, Action, ActionParameters, Status};
+    use crate::engine::world::Position;
+
+    fn rules() -> Vec<ScheduleRule> {
+        let work = Task::new(
+            Action::GoTo,
+            ActionParameters::at(Position { x: 10, y: 3 }),
+            50,
+        );
+        let park = Task::new(
+            Action::GoTo,
+            ActionParameters::at(Position { x: 40, y: 40 }),
+            30,
+        );
+        vec![
+            ScheduleRule {
+                days:   DaySelector::On(vec![Weekday::Sunday]),
+                hour:   12,
+                minute: 0,
+                task:   park,
+            },
+            ScheduleRule {
+                days:   DaySelector::Weekdays,
+                hour:   9,
+                minute: 0,
+                task:   work,
+            },
+        ]
+    }
+
+    #[test]
+    fn weekday_rules_expand_on_weekdays_only() {
+        let calendar = Calendar::default(); // Day 0 is a Monday
+        let monday = tasks_for_day(&rules(), &calendar, 0);
+        assert_eq!(monday.len(), 1);
+        assert_eq!(monday[0].time().get_stamp().hour, 9);
+        assert!(tasks_for_day(&rules(), &calendar, 5).is_empty());
+        let sunday = tasks_for_day(&rules(), &calendar, 6);
+        assert_eq!(sunday.len(), 1);
+        assert_eq!(sunday[0].time().get_stamp().day, 6);
+        assert_eq!(sunday[0].time().get_stamp().hour, 12);
+    }
+
+    #[test]
+    fn rules_fire_again_on_their_next_day() {
+        let calendar = Calendar::default();
+        let at = |day, hour, minute| {
+            GameTime::from_stamp(&Stamp {
+                day,
+                hour,
+                minute,
+                second: 0,
+            })
+        };
+        let status = Status::new(10);
+        let mut routine = Routine::default();
+        expand_day(&mut routine, &rules(), &calendar, at(0, 0, 0));
+        assert_eq!(
+            next_task(&status, &mut routine, at(0, 8, 59)).action(),
+            Action::Wait
+        );
+        assert_eq!(
+            next_task(&status, &mut routine, at(0, 9, 0)).action(),
+            Action::GoTo
+        );
+        // Taken off the Routine once done
+        assert_eq!(
+            next_task(&status, &mut routine, at(0, 9, 30)).action(),
+            Action::Wait
+        );
+        expand_day(&mut routine, &rules(), &calendar, at(1, 0, 0));
+        let tuesday = next_task(&status, &mut routine, at(1, 9, 0));
+        assert_eq!(tuesday.action(), Action::GoTo);
+        assert_eq!(
+            tuesday.parameters().location(),
+            Some(Position { x: 10, y: 3 })
+        );
+        assert!(routine.next().is_none());
+    }
+
+    #[test]
+    fn late_schedules_skip_past_rules() {
+        let calendar = Calendar::default();
+        let mut routine = Routine::default();
+        let noon = GameTime::from_stamp(&Stamp {
+            day:    0,
+            hour:   12,
+            minute: 0,
+            second: 0,
+        });
+        expand_day(&mut routine, &rules(), &calendar, noon);
+        assert!(routine.next().is_none());
+    }
+}
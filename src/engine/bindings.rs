@@ -0,0 +1,266 @@
+// Key bindings:
+// Maps the rebindable controls to keys. The defaults suit QWERTY keyboards;
+// assets/config/bindings.cfg overrides them one action per line, as in
+// "pan_up = W, Up", with keys named after bevy's KeyCode variants. Lines
+// starting with # are comments and actions left out keep their defaults.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+
+pub const BINDINGS_PATH: &str = "assets/config/bindings.cfg";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Control {
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    Follow, // Toggle following the selection or the player
+    Focus,  // Jump to the selection or the player once
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+}
+impl Control {
+    const ALL: [Control; 10] = [
+        Control::PanLeft,
+        Control::PanRight,
+        Control::PanUp,
+        Control::PanDown,
+        Control::Follow,
+        Control::Focus,
+        Control::MoveLeft,
+        Control::MoveRight,
+        Control::MoveUp,
+        Control::MoveDown,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Control::PanLeft => "pan_left",
+            Control::PanRight => "pan_right",
+            Control::PanUp => "pan_up",
+            Control::PanDown => "pan_down",
+            Control::Follow => "follow",
+            Control::Focus => "focus",
+            Control::MoveLeft => "move_left",
+            Control::MoveRight => "move_right",
+            Control::MoveUp => "move_up",
+            Control::MoveDown => "move_down",
+        }
+    }
+}
+
+// Keys that can be bound, looked up by their Debug name
+const KEYS: &[KeyCode] = &[
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Back,
+    KeyCode::Return,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::Slash,
+    KeyCode::Backslash,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Grave,
+    KeyCode::Numpad0,
+    KeyCode::Numpad1,
+    KeyCode::Numpad2,
+    KeyCode::Numpad3,
+    KeyCode::Numpad4,
+    KeyCode::Numpad5,
+    KeyCode::Numpad6,
+    KeyCode::Numpad7,
+    KeyCode::Numpad8,
+    KeyCode::Numpad9,
+    KeyCode::Space,
+    KeyCode::Tab,
+];
+
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    KEYS.iter()
+        .copied()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    keys: HashMap<Control, Vec<KeyCode>>,
+}
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut keys = HashMap::new();
+        keys.insert(Control::PanLeft, vec![KeyCode::A]);
+        keys.insert(Control::PanRight, vec![KeyCode::D]);
+        keys.insert(Control::PanUp, vec![KeyCode::W]);
+        keys.insert(Control::PanDown, vec![KeyCode::S]);
+        keys.insert(Control::Follow, vec![KeyCode::F]);
+        keys.insert(Control::Focus, vec![KeyCode::C]);
+        keys.insert(Control::MoveLeft, vec![KeyCode::Left]);
+        keys.insert(Control::MoveRight, vec![KeyCode::Right]);
+        keys.insert(Control::MoveUp, vec![KeyCode::Up]);
+        keys.insert(Control::MoveDown, vec![KeyCode::Down]);
+        Self { keys }
+    }
+}
+impl KeyBindings {
+    pub fn load_or_default(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(text) => text.parse().unwrap_or_else(|error| {
+                warn!("Ignoring {}: {}", path, error);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+    pub fn keys(
+        &self,
+        control: Control,
+    ) -> &[KeyCode] {
+        self.keys.get(&control).map_or(&[][..], Vec::as_slice)
+    }
+    pub fn pressed(
+        &self,
+        control: Control,
+        keyboard: &Input<KeyCode>,
+    ) -> bool {
+        self.keys(control).iter().any(|key| keyboard.pressed(*key))
+    }
+    pub fn just_pressed(
+        &self,
+        control: Control,
+        keyboard: &Input<KeyCode>,
+    ) -> bool {
+        self.keys(control)
+            .iter()
+            .any(|key| keyboard.just_pressed(*key))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBindingsError {
+    line:    usize,
+    message: String,
+}
+impl fmt::Display for ParseBindingsError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+impl std::error::Error for ParseBindingsError {}
+
+impl FromStr for KeyBindings {
+    type Err = ParseBindingsError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut bindings = Self::default();
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| ParseBindingsError {
+                line: index + 1,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, keys) = match line.find('=') {
+                Some(split) => (line[..split].trim(), &line[split + 1..]),
+                None => return Err(error("expected action = keys".to_owned())),
+            };
+            let control = Control::ALL
+                .iter()
+                .copied()
+                .find(|control| control.name() == name)
+                .ok_or_else(|| error(format!("unknown action {:?}", name)))?;
+            let keys = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| {
+                    key_code(key)
+                        .ok_or_else(|| error(format!("unknown key {:?}", key)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            bindings.keys.insert(control, keys);
+        }
+        Ok(bindings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_defaults_per_action() {
+        let bindings: KeyBindings = "# Dvorak\npan_up = Comma, up\npan_down = \
+                                     O\n\nfocus =\n"
+            .parse()
+            .unwrap();
+        assert_eq!(bindings.keys(Control::PanUp), &[
+            KeyCode::Comma,
+            KeyCode::Up
+        ]);
+        assert_eq!(bindings.keys(Control::PanDown), &[KeyCode::O]);
+        assert_eq!(bindings.keys(Control::PanLeft), &[KeyCode::A]);
+        assert!(bindings.keys(Control::Focus).is_empty());
+        assert_eq!(
+            "pan_up = W\nzoom = Z".parse::<KeyBindings>(),
+            Err(ParseBindingsError {
+                line:    2,
+                message: "unknown action \"zoom\"".to_owned(),
+            })
+        );
+        assert!("pan_up = Wheel".parse::<KeyBindings>().is_err());
+    }
+}
@@ -6,6 +6,9 @@ use bevy::prelude::*;
 pub mod render;
 // When pub people run in pub circles it's a very, very
 pub mod actor;
+pub mod bindings;
+pub mod tui;
+pub mod ui;
 pub mod world;
 
 pub use bevy_ecs_tilemap::prelude::*;
@@ -22,30 +25,44 @@ pub fn spawn_actor(
     commands: &mut Commands,
     identity: Identity,
     position: world::Position,
-    destination: world::Destination,
+    destination: Option<world::Destination>,
     sprite_sheet: SpriteSheetBundle,
-) {
-    commands
-        .spawn()
+) -> Entity {
+    let start = world::time::GameTime::from_stamp(&world::time::Stamp {
+        day:    0,
+        hour:   6,
+        minute: 0,
+        second: 0,
+    });
+    let mut actor = commands.spawn();
+    actor
         .insert(identity)
         .insert(position)
+        .insert(world::Occupancy::Blocking)
         .insert(actor::Orientation(actor::Direction::Down))
-        .insert(destination)
+        .insert(actor::LastStep {
+            from:       position,
+            at:         start,
+            sub_second: 0.0,
+            duration:   0.0,
+        })
         .insert_bundle(sprite_sheet)
-        .insert(world::time::GameTime::from_stamp(&world::time::Stamp {
-            day:    0,
-            hour:   6,
-            minute: 0,
-            second: 0,
-        }));
+        .insert(render::lighting::BaseColor::default())
+        .insert(render::animation::AnimationState::default());
+    if let Some(destination) = destination {
+        actor.insert(destination);
+    }
+    actor.id()
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 pub struct Identity {
     pub specific: bool,
     pub name:     String,
 }
 
+pub struct Selected; // Marks the entity shown by inspection tools
+
 #[cfg(test)]
 mod tests {
     #[test]
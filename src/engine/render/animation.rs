@@ -0,0 +1,175 @@
+// Sprite animation:
+// Every sprite sheet is described by a SpriteSheetMeta: the grid its atlas is
+// sliced into and, per facing, the frames of the walk cycle and the idle
+// frame. Actors play the walk cycle of their Orientation while they have a
+// Path and show the idle frame otherwise. Frames are timed in game seconds,
+// so walking speeds up, slows down and pauses with the game clock.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use crate::engine::actor::Direction;
+use crate::engine::world::time::GameTime;
+
+pub const NPC_SHEET: &str = "sprites/NPC1 (2).png";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation {
+    pub walk: Range<u32>, // Atlas indices, played in order and looped
+    pub idle: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpriteSheetMeta {
+    pub frame_size:     Vec2, // Pixels
+    pub columns:        usize,
+    pub rows:           usize,
+    pub frame_duration: f32, // Game seconds each walk frame is shown
+    pub animations:     HashMap<Direction, Animation>,
+}
+impl SpriteSheetMeta {
+    pub fn npc() -> Self {
+        // 256 px frames, one row per facing; the last row only has two
+        let mut animations = HashMap::new();
+        animations.insert(Direction::Down, Animation {
+            walk: 0..4,
+            idle: 0,
+        });
+        animations.insert(Direction::Up, Animation {
+            walk: 4..8,
+            idle: 4,
+        });
+        animations.insert(Direction::Left, Animation {
+            walk: 8..12,
+            idle: 9,
+        });
+        animations.insert(Direction::Right, Animation {
+            walk: 12..14,
+            idle: 12,
+        });
+        Self {
+            frame_size: Vec2::splat(256.0),
+            columns: 4,
+            rows: 4,
+            frame_duration: 0.25, // Actors step once a second, a cycle per tile
+            animations,
+        }
+    }
+    pub fn animation(
+        &self,
+        direction: Direction,
+    ) -> Option<&Animation> {
+        // Sheets without diagonal frames show the vertical facing
+        self.animations.get(&direction).or_else(|| {
+            let vertical = match direction {
+                Direction::UpLeft | Direction::UpRight => Direction::Up,
+                Direction::DownLeft | Direction::DownRight => Direction::Down,
+                other => other,
+            };
+            self.animations.get(&vertical)
+        })
+    }
+    pub fn frame(
+        &self,
+        direction: Direction,
+        walked: Option<f32>,
+    ) -> u32 {
+        // Atlas index after walking for `walked` game seconds, None if idle
+        let animation = match self.animation(direction) {
+            Some(animation) => animation,
+            None => return 0,
+        };
+        match walked {
+            Some(seconds)
+                if !animation.walk.is_empty() && self.frame_duration > 0.0 =>
+            {
+                let frames = animation.walk.len() as u32;
+                let frame = (seconds.max(0.0) / self.frame_duration) as u32;
+                animation.walk.start + frame % frames
+            }
+            _ => animation.idle,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AnimationState {
+    pub walking_since: Option<GameTime>, // Start of the current walk cycle
+}
+
+pub struct SpriteSheets {
+    metadata: HashMap<String, SpriteSheetMeta>, // By asset path
+    atlases:  HashMap<Handle<TextureAtlas>, String>,
+}
+impl Default for SpriteSheets {
+    fn default() -> Self {
+        let mut sheets = Self {
+            metadata: HashMap::new(),
+            atlases:  HashMap::new(),
+        };
+        sheets.register(NPC_SHEET, SpriteSheetMeta::npc());
+        sheets
+    }
+}
+impl SpriteSheets {
+    pub fn register(
+        &mut self,
+        path: &str,
+        meta: SpriteSheetMeta,
+    ) {
+        self.metadata.insert(path.to_owned(), meta);
+    }
+    pub fn atlas(
+        &mut self,
+        path: &str,
+        asset_server: &AssetServer,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Option<Handle<TextureAtlas>> {
+        // Sliced once per sheet and shared by every sprite using it
+        if let Some((handle, _)) = self
+            .atlases
+            .iter()
+            .find(|(_, atlas_path)| *atlas_path == path)
+        {
+            return Some(handle.clone());
+        }
+        let meta = self.metadata.get(path)?;
+        let handle = texture_atlases.add(TextureAtlas::from_grid(
+            asset_server.load(path),
+            meta.frame_size,
+            meta.columns,
+            meta.rows,
+        ));
+        self.atlases.insert(handle.clone(), path.to_owned());
+        Some(handle)
+    }
+    pub fn meta(
+        &self,
+        atlas: &Handle<TextureAtlas>,
+    ) -> Option<&SpriteSheetMeta> {
+        self.atlases
+            .get(atlas)
+            .and_then(|path| self.metadata.get(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_cycle_loops_and_idles() {
+        let meta = SpriteSheetMeta::npc();
+        assert_eq!(meta.frame(Direction::Up, None), 4);
+        assert_eq!(meta.frame(Direction::Left, Some(0.0)), 8);
+        assert_eq!(meta.frame(Direction::Left, Some(0.6)), 10);
+        assert_eq!(meta.frame(Direction::Left, Some(1.1)), 8);
+        assert_eq!(meta.frame(Direction::Right, Some(0.3)), 13);
+        assert_eq!(meta.frame(Direction::Right, Some(0.5)), 12);
+        // Diagonals borrow the vertical facing
+        assert_eq!(meta.frame(Direction::DownRight, Some(0.8)), 3);
+        assert_eq!(meta.frame(Direction::UpLeft, None), 4);
+    }
+}
@@ -0,0 +1,190 @@
+// Speech bubbles:
+// Short world-space labels above actors for the ActorEvents worth showing,
+// such as starting to eat, greeting someone or failing to find a path. A
+// bubble lasts a few game seconds, so it pauses with the clock, and a new one
+// replaces whatever the actor was saying. Bubbles keep the same size on screen
+// at any zoom.
+
+use bevy::prelude::*;
+
+use crate::engine::actor::{Action, ActorEvent};
+use crate::engine::render::{layers::RenderLayer, MainCamera, Projection};
+use crate::engine::world::time::{GameDuration, GameTime};
+use crate::engine::Identity;
+
+const BUBBLE_DURATION: GameDuration = GameDuration::from_seconds(4);
+const FONT_SIZE: f32 = 16.0;
+const SAY_COLOR: Color = Color::WHITE;
+const TROUBLE_COLOR: Color = Color::rgb(1.0, 0.45, 0.4);
+
+pub struct SpeechBubble {
+    speaker: Entity,
+    until:   GameTime,
+}
+
+pub fn bubble_text(
+    event: &ActorEvent,
+    name_of: impl Fn(Entity) -> Option<String>,
+) -> Option<(Entity, String, Color)> {
+    // The speaker, what they say and its colour; None for quiet events
+    match *event {
+        ActorEvent::Began {
+            actor,
+            action: Action::Eat,
+        } => Some((actor, "Time to eat".to_owned(), SAY_COLOR)),
+        ActorEvent::Greeted { actor, other } => {
+            let text = match name_of(other) {
+                Some(name) => format!("Hello, {}!", name),
+                None => "Hello!".to_owned(),
+            };
+            Some((actor, text, SAY_COLOR))
+        }
+        ActorEvent::NoPath { actor, .. } => {
+            Some((actor, "I can't get there".to_owned(), TROUBLE_COLOR))
+        }
+        ActorEvent::Began { .. } | ActorEvent::Arrived { .. } => None,
+    }
+}
+
+pub fn spawn_bubbles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game_time: Res<GameTime>,
+    mut events: EventReader<ActorEvent>,
+    identities: Query<&Identity>,
+    bubbles: Query<(Entity, &SpeechBubble)>,
+) {
+    let name_of = |entity| {
+        identities
+            .get(entity)
+            .ok()
+            .filter(|identity| identity.specific)
+            .map(|identity| identity.name.clone())
+    };
+    for event in events.iter() {
+        let (speaker, text, color) = match bubble_text(event, name_of) {
+            Some(bubble) => bubble,
+            None => continue,
+        };
+        for (entity, bubble) in bubbles.iter() {
+            if bubble.speaker == speaker {
+                commands.entity(entity).despawn();
+            }
+        }
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    text,
+                    TextStyle {
+                        font: asset_server.load("fonts/OpenSans-Bold.ttf"),
+                        font_size: FONT_SIZE,
+                        color,
+                    },
+                    TextAlignment {
+                        vertical:   VerticalAlign::Bottom,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                // Shown once placed above the speaker
+                visible: Visible {
+                    is_visible:     false,
+                    is_transparent: true,
+                },
+                ..Default::default()
+            })
+            .insert(SpeechBubble {
+                speaker,
+                until: game_time.copy_and_tick(BUBBLE_DURATION),
+            });
+    }
+}
+
+pub fn update_bubbles(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    projection: Res<Projection>,
+    speakers: Query<&Transform, Without<SpeechBubble>>,
+    cameras: Query<&Transform, (With<MainCamera>, Without<SpeechBubble>)>,
+    mut bubbles: Query<(Entity, &SpeechBubble, &mut Transform, &mut Visible)>,
+) {
+    // Runs after the camera has moved so bubbles do not trail the zoom
+    let scale = cameras
+        .iter()
+        .next()
+        .map_or(Vec3::ONE, |camera| camera.scale);
+    let lift = projection.tile_size().y * 0.6;
+    for (entity, bubble, mut transform, mut visible) in bubbles.iter_mut() {
+        let speaker = match speakers.get(bubble.speaker) {
+            Ok(speaker) if *game_time < bubble.until => speaker,
+            _ => {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        };
+        transform.translation = Vec3::new(
+            speaker.translation.x,
+            speaker.translation.y + lift,
+            RenderLayer::Overhead.z(),
+        );
+        transform.scale = scale;
+        visible.is_visible = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::Position;
+
+    #[test]
+    fn greetings_name_whoever_is_known() {
+        let (actor, other) = (Entity::new(1), Entity::new(2));
+        let greeting = ActorEvent::Greeted { actor, other };
+        assert_eq!(
+            bubble_text(&greeting, |_| Some("Grumph".to_owned()))
+                .map(|(speaker, text, _)| (speaker, text)),
+            Some((actor, "Hello, Grumph!".to_owned()))
+        );
+        assert_eq!(
+            bubble_text(&greeting, |_| None).map(|(_, text, _)| text),
+            Some("Hello!".to_owned())
+        );
+    }
+
+    #[test]
+    fn eating_is_announced() {
+        let actor = Entity::new(1);
+        let eating = ActorEvent::Began {
+            actor,
+            action: Action::Eat,
+        };
+        assert_eq!(
+            bubble_text(&eating, |_| None),
+            Some((actor, "Time to eat".to_owned(), SAY_COLOR))
+        );
+    }
+
+    #[test]
+    fn quiet_events_make_no_bubble() {
+        let actor = Entity::new(1);
+        let named = |_| Some("Grumph".to_owned());
+        let going = ActorEvent::Began {
+            actor,
+            action: Action::GoTo,
+        };
+        assert!(bubble_text(&going, named).is_none());
+        let arrived = ActorEvent::Arrived {
+            actor,
+            at: Position { x: 1, y: 2 },
+        };
+        assert!(bubble_text(&arrived, named).is_none());
+        let lost = ActorEvent::NoPath {
+            actor,
+            to: Position { x: 1, y: 2 },
+        };
+        assert_eq!(
+            bubble_text(&lost, named).map(|(_, text, color)| (text, color)),
+            Some(("I can't get there".to_owned(), TROUBLE_COLOR))
+        );
+    }
+}
@@ -1,55 +1,191 @@
 // This system handles user input control of the camera.
+// The pan keys come from KeyBindings; panning drops the follow target. The
+// camera starts out following the player, the follow key toggles following
+// the Selected actor (or the player when nothing is selected) and the focus
+// key jumps to it once. The scroll wheel zooms smoothly
+// about the cursor and the camera's centre never leaves the loaded map.
 
 use bevy::input::mouse::MouseWheel;
-use bevy::{prelude::*, render::camera::Camera};
+use bevy::prelude::*;
+
+use crate::engine::actor::player::Player;
+use crate::engine::bindings::{Control, KeyBindings};
+use crate::engine::render::{MainCamera, Projection};
+use crate::engine::world::TileWeightMap;
+use crate::engine::Selected;
+
+const PAN_SPEED: f32 = 200.0; // Screen pixels per second at any zoom
+const ZOOM_STEP: f32 = 1.15; // Scale factor per wheel line
+const ZOOM_RATE: f32 = 10.0; // How quickly the scale eases to its target
+const FOLLOW_RATE: f32 = 5.0;
+const MIN_SCALE: f32 = 0.5;
+const MAX_SCALE: f32 = 40.0;
+
+pub struct CameraControl {
+    pub follow: Option<Entity>,
+    zoom:       f32, // Scale the camera eases towards
+}
+impl Default for CameraControl {
+    fn default() -> Self {
+        Self {
+            follow: None,
+            zoom:   5.0,
+        }
+    }
+}
+
+pub fn zoom_about(
+    translation: Vec2,
+    anchor: Vec2,
+    scale: f32,
+    new_scale: f32,
+) -> Vec2 {
+    // Keeps the world point `anchor` screen pixels from the centre in place
+    translation + anchor * (scale - new_scale)
+}
+
+pub fn clamp_to_bounds(
+    point: Vec2,
+    min: Vec2,
+    max: Vec2,
+) -> Vec2 {
+    point.max(min).min(max)
+}
+
+pub fn camera_controls(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    mut control: ResMut<CameraControl>,
+    selected: Query<Entity, With<Selected>>,
+    players: Query<Entity, With<Player>>,
+    new_players: Query<Entity, Added<Player>>,
+    mut cameras: QuerySet<(
+        Query<&mut Transform, With<MainCamera>>,
+        Query<&Transform, Without<MainCamera>>,
+    )>,
+) {
+    if let Some(player) = new_players.iter().next() {
+        control.follow = Some(player);
+    }
+    let selection = selected.iter().next().or_else(|| players.iter().next());
+    if bindings.just_pressed(Control::Follow, &keyboard) {
+        control.follow = match control.follow {
+            Some(_) => None,
+            None => selection,
+        };
+    }
+    if !bindings.just_pressed(Control::Focus, &keyboard) {
+        return;
+    }
+    let target = match selection
+        .and_then(|entity| cameras.q1().get(entity).ok())
+        .map(|transform| transform.translation)
+    {
+        Some(target) => target,
+        None => return,
+    };
+    for mut transform in cameras.q0_mut().iter_mut() {
+        transform.translation.x = target.x;
+        transform.translation.y = target.y;
+    }
+}
 
 pub fn camera_movement(
     time: Res<Time>,
     keyboard_input: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
     mut scroll_events: EventReader<MouseWheel>,
-    mut query: Query<&mut Transform, With<Camera>>,
+    windows: Res<Windows>,
+    projection: Res<Projection>,
+    weight_map: Res<TileWeightMap>,
+    mut control: ResMut<CameraControl>,
+    interactions: Query<&Interaction>,
+    mut cameras: QuerySet<(
+        Query<&mut Transform, With<MainCamera>>,
+        Query<&Transform, Without<MainCamera>>,
+    )>,
 ) {
-    for mut transform in &mut query.iter_mut() {
-        let mut direction = Vec3::ZERO;
-
+    let delta = time.delta_seconds();
+    let mut direction = Vec2::ZERO;
+    if bindings.pressed(Control::PanLeft, &keyboard_input) {
+        direction -= Vec2::new(1.0, 0.0);
+    }
+    if bindings.pressed(Control::PanRight, &keyboard_input) {
+        direction += Vec2::new(1.0, 0.0);
+    }
+    if bindings.pressed(Control::PanUp, &keyboard_input) {
+        direction += Vec2::new(0.0, 1.0);
+    }
+    if bindings.pressed(Control::PanDown, &keyboard_input) {
+        direction -= Vec2::new(0.0, 1.0);
+    }
+    if direction != Vec2::ZERO {
+        control.follow = None;
+    }
+    // The wheel scrolls UI nodes under the cursor instead
+    let over_ui = interactions
+        .iter()
+        .any(|interaction| *interaction != Interaction::None);
+    for event in scroll_events.iter().filter(|_| !over_ui) {
+        control.zoom = (control.zoom * ZOOM_STEP.powf(-event.y))
+            .clamp(MIN_SCALE, MAX_SCALE);
+    }
+    let target = control
+        .follow
+        .and_then(|entity| cameras.q1().get(entity).ok())
+        .map(|transform| transform.translation.truncate());
+    if control.follow.is_some() && target.is_none() {
+        control.follow = None; // Despawned
+    }
+    // Zoom about the cursor, or about the centre when following
+    let anchor = match (windows.get_primary(), target) {
+        (Some(window), None) => window.cursor_position().map(|cursor| {
+            cursor - Vec2::new(window.width(), window.height()) / 2.0
+        }),
+        _ => None,
+    }
+    .unwrap_or(Vec2::ZERO);
+    let (map_min, map_max) =
+        projection.map_bounds(weight_map.width(), weight_map.height());
+    for mut transform in cameras.q0_mut().iter_mut() {
         let scale = transform.scale.x;
-        if keyboard_input.pressed(KeyCode::A) {
-            direction -= Vec3::new(1.0, 0.0, 0.0);
-        }
-
-        if keyboard_input.pressed(KeyCode::E) {
-            direction += Vec3::new(1.0, 0.0, 0.0);
-        }
-
-        if keyboard_input.pressed(KeyCode::Comma) {
-            direction += Vec3::new(0.0, 1.0, 0.0);
-        }
-
-        if keyboard_input.pressed(KeyCode::O) {
-            direction -= Vec3::new(0.0, 1.0, 0.0);
-        }
-        for event in scroll_events.iter() {
-            let scale = scale - event.y / 5.0;
-            transform.scale = Vec3::splat(scale);
-        }
-        if transform.scale.x < 5.0 {
-            transform.scale = Vec3::splat(5.0);
-        }
-        direction *= transform.scale.x;
-        // Add world bounds (Temporary until camera follows player)
-        let mut planned_translation =
-            transform.translation + time.delta_seconds() * direction * 200.0;
-        let out_of_bounds_x = transform.scale.x * 250.0 - planned_translation.x;
-        let out_of_bounds_y = transform.scale.y * 250.0 - planned_translation.y;
-
-        if out_of_bounds_x > 0.0 {
-            planned_translation += Vec3::new(out_of_bounds_x, 0.0, 0.0);
+        let new_scale =
+            scale + (control.zoom - scale) * (delta * ZOOM_RATE).min(1.0);
+        let mut translation = zoom_about(
+            transform.translation.truncate(),
+            anchor,
+            scale,
+            new_scale,
+        );
+        translation += direction * new_scale * PAN_SPEED * delta;
+        if let Some(target) = target {
+            translation +=
+                (target - translation) * (delta * FOLLOW_RATE).min(1.0);
         }
+        let translation = clamp_to_bounds(translation, map_min, map_max);
+        transform.scale = Vec3::new(new_scale, new_scale, transform.scale.z);
+        transform.translation = translation.extend(transform.translation.z);
+    }
+}
 
-        if out_of_bounds_y > 0.0 {
-            planned_translation += Vec3::new(0.0, out_of_bounds_y, 0.0);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        transform.translation = planned_translation;
+    #[test]
+    fn zoom_keeps_the_anchor_in_place() {
+        let (translation, anchor) =
+            (Vec2::new(100.0, 50.0), Vec2::new(40.0, -20.0));
+        let world_point = translation + anchor * 2.0;
+        let zoomed = zoom_about(translation, anchor, 2.0, 0.5);
+        assert_eq!(zoomed + anchor * 0.5, world_point);
+        assert_eq!(
+            clamp_to_bounds(
+                Vec2::new(-5.0, 500.0),
+                Vec2::ZERO,
+                Vec2::new(100.0, 100.0)
+            ),
+            Vec2::new(0.0, 100.0)
+        );
     }
 }
@@ -0,0 +1,264 @@
+// Heatmaps:
+// F6 cycles an overlay over the tilemap through the tile weights, the current
+// occupancy and the traffic of the last game hour; blue is low, red is high
+// and tiles without a value stay clear. The layer is one texture rasterised
+// at half-tile resolution over the map's bounds, each pixel coloured after
+// the tile under its centre, so it lines up with every projection. F7 exports
+// the traffic grid to traffic.csv and traffic.png.
+
+use std::path::Path as FilePath;
+
+use bevy::{prelude::*,
+           render::texture::{Extent3d, FilterMode, TextureDimension,
+                             TextureFormat}};
+
+use crate::engine::render::{layers::RenderLayer, Projection};
+use crate::engine::world::{traffic::TrafficMap, Position, TileEntityMap,
+                           TileWeightMap};
+
+const HEATMAP_ALPHA: u8 = 150;
+const REFRESH_SECONDS: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum HeatmapLayer {
+    Off,
+    Weights,
+    Occupancy,
+    Traffic,
+}
+impl HeatmapLayer {
+    pub fn next(self) -> Self {
+        match self {
+            HeatmapLayer::Off => HeatmapLayer::Weights,
+            HeatmapLayer::Weights => HeatmapLayer::Occupancy,
+            HeatmapLayer::Occupancy => HeatmapLayer::Traffic,
+            HeatmapLayer::Traffic => HeatmapLayer::Off,
+        }
+    }
+}
+impl Default for HeatmapLayer {
+    fn default() -> Self { HeatmapLayer::Off }
+}
+
+#[derive(Default)]
+pub struct Heatmap {
+    pub layer: HeatmapLayer,
+    texture:   Option<Handle<Texture>>, // Created when first shown
+}
+
+pub struct HeatmapSprite;
+
+pub fn heat_color(heat: Option<f32>) -> [u8; 4] {
+    // Blue through green to red
+    let heat = match heat {
+        Some(heat) => heat.clamp(0.0, 1.0),
+        None => return [0; 4],
+    };
+    let (r, g, b) = if heat < 0.5 {
+        (0.0, heat * 2.0, 1.0 - heat * 2.0)
+    } else {
+        (heat * 2.0 - 1.0, 2.0 - heat * 2.0, 0.0)
+    };
+    let channel = |value: f32| (value * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b), HEATMAP_ALPHA]
+}
+
+pub fn layer_values(
+    layer: HeatmapLayer,
+    weight_map: &TileWeightMap,
+    entity_map: &TileEntityMap,
+    traffic: &TrafficMap,
+) -> Vec<Option<f32>> {
+    // One value in [0, 1] per tile, row by row from y = 0
+    let (width, height) = (weight_map.width(), weight_map.height());
+    let tiles = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+    match layer {
+        HeatmapLayer::Off => vec![None; (width * height) as usize],
+        HeatmapLayer::Weights => {
+            // Obstacles are left clear, they would drown out every other cost
+            let max = weight_map
+                .map
+                .iter()
+                .copied()
+                .filter(|weight| *weight < i64::MAX)
+                .max()
+                .unwrap_or(1)
+                .max(1);
+            tiles
+                .map(|(x, y)| {
+                    let weight = weight_map.get(x, y);
+                    if weight < i64::MAX {
+                        Some(weight as f32 / max as f32)
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        }
+        HeatmapLayer::Occupancy => tiles
+            .map(|(x, y)| match entity_map.occupants(x, y) {
+                Some(occupants) if occupants.blocking.is_some() => Some(1.0),
+                Some(occupants) if !occupants.non_blocking.is_empty() => {
+                    Some(0.4)
+                }
+                _ => None,
+            })
+            .collect(),
+        HeatmapLayer::Traffic => {
+            let grid = traffic.grid();
+            let max = grid.iter().copied().max().unwrap_or(0).max(1);
+            grid.iter()
+                .map(|count| match count {
+                    0 => None,
+                    count => Some(*count as f32 / max as f32),
+                })
+                .collect()
+        }
+    }
+}
+
+pub fn rasterize(
+    projection: &Projection,
+    width: i64,
+    height: i64,
+    values: &[Option<f32>],
+) -> (Vec<u8>, u32, u32) {
+    // RGBA pixels (top row first) covering map_bounds, and their dimensions
+    let (min, max) = projection.map_bounds(width, height);
+    let cell = projection.tile_size() / 2.0;
+    let columns = ((max.x - min.x) / cell.x).ceil() as u32;
+    let rows = ((max.y - min.y) / cell.y).ceil() as u32;
+    let mut data = Vec::with_capacity((columns * rows * 4) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            let centre = Vec2::new(
+                min.x + (column as f32 + 0.5) * cell.x,
+                max.y - (row as f32 + 0.5) * cell.y,
+            );
+            let Position { x, y } = projection.to_tile(centre);
+            let value = if 0 <= x && x < width && 0 <= y && y < height {
+                values[(y * width + x) as usize]
+            } else {
+                None
+            };
+            data.extend_from_slice(&heat_color(value));
+        }
+    }
+    (data, columns, rows)
+}
+
+pub fn update_heatmap(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut until_refresh: Local<f32>,
+    mut heatmap: ResMut<Heatmap>,
+    projection: Res<Projection>,
+    weight_map: Res<TileWeightMap>,
+    entity_map: Res<TileEntityMap>,
+    traffic: Res<TrafficMap>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut sprites: Query<&mut Visible, With<HeatmapSprite>>,
+) {
+    let switched = keyboard.just_pressed(KeyCode::F6);
+    if switched {
+        heatmap.layer = heatmap.layer.next();
+        info!("Heatmap: {:?}", heatmap.layer);
+    }
+    for mut visible in sprites.iter_mut() {
+        visible.is_visible = heatmap.layer != HeatmapLayer::Off;
+    }
+    *until_refresh -= time.delta_seconds();
+    if heatmap.layer == HeatmapLayer::Off || (!switched && *until_refresh > 0.0)
+    {
+        return;
+    }
+    *until_refresh = REFRESH_SECONDS;
+    let values =
+        layer_values(heatmap.layer, &weight_map, &entity_map, &traffic);
+    let (data, columns, rows) = rasterize(
+        &projection,
+        weight_map.width(),
+        weight_map.height(),
+        &values,
+    );
+    if let Some(texture) = heatmap
+        .texture
+        .as_ref()
+        .and_then(|handle| textures.get_mut(handle))
+    {
+        texture.data = data;
+        return;
+    }
+    let mut texture = Texture::new(
+        Extent3d::new(columns, rows, 1),
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    texture.sampler.mag_filter = FilterMode::Nearest;
+    texture.sampler.min_filter = FilterMode::Nearest;
+    let handle = textures.add(texture);
+    let (min, max) =
+        projection.map_bounds(weight_map.width(), weight_map.height());
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(ColorMaterial::texture(handle.clone())),
+            sprite: Sprite::new(max - min),
+            transform: Transform::from_translation(
+                ((min + max) / 2.0).extend(RenderLayer::Decals.z()),
+            ),
+            visible: Visible {
+                is_visible:     true,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(HeatmapSprite);
+    heatmap.texture = Some(handle);
+}
+
+pub fn export_traffic(
+    keyboard: Res<Input<KeyCode>>,
+    traffic: Res<TrafficMap>,
+) {
+    if !keyboard.just_pressed(KeyCode::F7) {
+        return;
+    }
+    match std::fs::write("traffic.csv", traffic.to_csv()) {
+        Ok(()) => info!("Exported traffic.csv"),
+        Err(error) => warn!("Could not export traffic.csv: {}", error),
+    }
+    match traffic.save_png(FilePath::new("traffic.png")) {
+        Ok(()) => info!("Exported traffic.png"),
+        Err(error) => warn!("Could not export traffic.png: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raster_follows_tiles() {
+        let projection = Projection::Orthogonal {
+            tile_size: Vec2::splat(2.0),
+        };
+        let (data, columns, rows) =
+            rasterize(&projection, 2, 1, &[Some(0.0), Some(1.0)]);
+        assert_eq!((columns, rows), (4, 2));
+        let pixel = |column: usize, row: usize| {
+            let start = (row * columns as usize + column) * 4;
+            [
+                data[start],
+                data[start + 1],
+                data[start + 2],
+                data[start + 3],
+            ]
+        };
+        assert_eq!(pixel(0, 0), [0, 0, 255, HEATMAP_ALPHA]);
+        assert_eq!(pixel(3, 1), [255, 0, 0, HEATMAP_ALPHA]);
+        assert_eq!(heat_color(None), [0; 4]);
+    }
+}
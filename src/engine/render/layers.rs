@@ -0,0 +1,50 @@
+// Render layers:
+// Every world-space sprite is drawn in one of these layers, bottom to top,
+// each owning one unit of z. Within a layer sprites may be depth sorted by
+// their height on screen so lower ones draw in front, which keeps actors
+// overlapping correctly on both orthogonal and isometric maps.
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderLayer {
+    Ground,   // The tilemap
+    Shade,    // Night overlay and the glow of lights
+    Decals,   // Heatmaps and the selection highlight
+    Items,    // Things lying on tiles
+    Actors,   // Walking sprites, depth sorted
+    Overhead, // Labels and bubbles above actors
+    Overlays, // Debug drawing over everything else
+}
+
+const SORT_SPAN: f32 = 0.9; // Of each layer's unit, leaving room for offsets
+
+impl RenderLayer {
+    pub fn z(self) -> f32 {
+        // The tilemap sits at 0.0, where bevy_ecs_tilemap puts it
+        self as u8 as f32
+    }
+    pub fn sorted(
+        self,
+        depth: f32,
+    ) -> f32 {
+        // z for a Projection::depth in [0, 1], nearer the viewer is higher
+        self.z() + depth.clamp(0.0, 1.0) * SORT_SPAN
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_do_not_interleave() {
+        assert_eq!(RenderLayer::Ground.z(), 0.0);
+        assert!(
+            RenderLayer::Actors.sorted(0.2) < RenderLayer::Actors.sorted(0.3)
+        );
+        assert!(
+            RenderLayer::Items.sorted(1.0) < RenderLayer::Actors.sorted(0.0)
+        );
+        assert!(RenderLayer::Actors.sorted(1.0) < RenderLayer::Overhead.z());
+        assert!(RenderLayer::Shade.sorted(2.0) < RenderLayer::Decals.z());
+    }
+}
@@ -0,0 +1,335 @@
+// Day-night lighting:
+// The ambient tint follows the clock, interpolated between the keyframes of
+// DayNightCycle (night, dawn, noon, dusk). Actor sprites are their BaseColor
+// multiplied by the tint, recoloured only when either changes; the tilemap is
+// darkened by a translucent overlay drawn between the ground and the actors.
+// LightSources (street lamps, windows) are glow sprites that fade in once it
+// gets dark enough. Street lamps stand on the kerb, the walkable tiles beside
+// a street.
+
+use bevy::prelude::*;
+
+use crate::engine::render::{layers::RenderLayer, Projection};
+use crate::engine::world::{time::GameTime, GroundType, Position, TileWeightMap};
+
+const GLOW_OFFSET: f32 = 0.5; // Glows lighten the overlay, in the same layer
+const OVERLAY_COLOR: (f32, f32, f32) = (0.02, 0.03, 0.12);
+const MAX_OVERLAY_ALPHA: f32 = 0.75;
+
+pub struct DayNightCycle {
+    keyframes: Vec<(f32, Color)>, // (hour of day, tint), sorted by hour
+}
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        let night = Color::rgb(0.25, 0.3, 0.5);
+        Self::new(vec![
+            (4.5, night),
+            (6.5, Color::rgb(1.0, 0.75, 0.6)), // Dawn
+            (12.0, Color::WHITE),              // Noon
+            (18.5, Color::rgb(1.0, 0.6, 0.45)), // Dusk
+            (20.5, night),
+        ])
+    }
+}
+impl DayNightCycle {
+    pub fn new(mut keyframes: Vec<(f32, Color)>) -> Self {
+        assert!(!keyframes.is_empty(), "Day-night cycle needs a keyframe");
+        keyframes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        Self { keyframes }
+    }
+    pub fn tint_at(
+        &self,
+        hour: f32,
+    ) -> Color {
+        // The cycle wraps around midnight, from the last keyframe to the first
+        let hour = hour.rem_euclid(24.0);
+        let next = self
+            .keyframes
+            .iter()
+            .position(|(key, _)| *key > hour)
+            .unwrap_or(0);
+        let previous = (next + self.keyframes.len() - 1) % self.keyframes.len();
+        let (from_hour, from) = self.keyframes[previous];
+        let (to_hour, to) = self.keyframes[next];
+        let span = (to_hour - from_hour).rem_euclid(24.0);
+        if span == 0.0 {
+            return from;
+        }
+        let t = (hour - from_hour).rem_euclid(24.0) / span;
+        Color::rgb(
+            from.r() + (to.r() - from.r()) * t,
+            from.g() + (to.g() - from.g()) * t,
+            from.b() + (to.b() - from.b()) * t,
+        )
+    }
+}
+
+pub struct AmbientLight {
+    pub tint:     Color,
+    pub darkness: f32, // 0 at noon, approaching 1 in the dead of night
+}
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self {
+            tint:     Color::WHITE,
+            darkness: 0.0,
+        }
+    }
+}
+impl AmbientLight {
+    pub fn from_tint(tint: Color) -> Self {
+        let luminance =
+            0.2126 * tint.r() + 0.7152 * tint.g() + 0.0722 * tint.b();
+        Self {
+            tint,
+            darkness: (1.0 - luminance).clamp(0.0, 1.0),
+        }
+    }
+}
+
+pub struct LightSource {
+    pub color:     Color, // Alpha is the glow's opacity at full darkness
+    pub radius:    f32,   // In tiles
+    pub threshold: f32,   // Darkness at which the light switches on
+}
+impl Default for LightSource {
+    fn default() -> Self {
+        Self {
+            color:     Color::rgba(1.0, 0.85, 0.5, 0.35),
+            radius:    2.5,
+            threshold: 0.3,
+        }
+    }
+}
+impl LightSource {
+    pub fn intensity(
+        &self,
+        darkness: f32,
+    ) -> f32 {
+        // Fades in over the darkness just above the threshold
+        if darkness <= self.threshold {
+            0.0
+        } else {
+            ((darkness - self.threshold) / 0.2).min(1.0)
+        }
+    }
+}
+
+// A sprite's own colour, which the ambient tint is applied on top of
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BaseColor(pub Color);
+impl Default for BaseColor {
+    fn default() -> Self { Self(Color::WHITE) }
+}
+
+pub fn modulate(
+    base: Color,
+    tint: Color,
+) -> Color {
+    Color::rgba(
+        base.r() * tint.r(),
+        base.g() * tint.g(),
+        base.b() * tint.b(),
+        base.a(),
+    )
+}
+
+pub fn kerbside_lamps(
+    weight_map: &TileWeightMap,
+    spacing: i64,
+) -> Vec<Position> {
+    // Scans row by row, keeping each kerb tile no nearer than spacing to the
+    // lamps already placed
+    let street = GroundType::Street.weight();
+    let crosswalk = GroundType::Crosswalk.weight();
+    let mut lamps: Vec<Position> = Vec::new();
+    for y in 0..weight_map.height() {
+        for x in 0..weight_map.width() {
+            let weight = weight_map.get(x, y);
+            if weight == i64::MAX || weight == street || weight == crosswalk {
+                continue;
+            }
+            let kerb = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                .iter()
+                .any(|(dx, dy)| weight_map.get(x + dx, y + dy) == street);
+            let position = Position { x, y };
+            if kerb
+                && lamps.iter().all(|lamp| {
+                    lamp.distance_squared(&position) >= spacing * spacing
+                })
+            {
+                lamps.push(position);
+            }
+        }
+    }
+    lamps
+}
+
+pub struct NightOverlay;
+
+pub fn spawn_night_overlay(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    projection: Res<Projection>,
+    weight_map: Res<TileWeightMap>,
+) {
+    let (min, max) =
+        projection.map_bounds(weight_map.width(), weight_map.height());
+    let (r, g, b) = OVERLAY_COLOR;
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::rgba(r, g, b, 0.0).into()),
+            sprite: Sprite::new(max - min),
+            transform: Transform::from_translation(
+                ((min + max) / 2.0).extend(RenderLayer::Shade.z()),
+            ),
+            visible: Visible {
+                is_visible:     true,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(NightOverlay);
+}
+
+pub fn spawn_light_source(
+    commands: &mut Commands,
+    materials: &mut Assets<ColorMaterial>,
+    projection: &Projection,
+    position: Position,
+    light: LightSource,
+) -> Entity {
+    let size = projection.tile_size() * light.radius * 2.0;
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(light.color.into()),
+            sprite: Sprite::new(size),
+            transform: Transform::from_translation(
+                projection
+                    .to_world(&position)
+                    .extend(RenderLayer::Shade.z() + GLOW_OFFSET),
+            ),
+            visible: Visible {
+                is_visible:     false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(light)
+        .id()
+}
+
+pub fn update_ambient_light(
+    game_time: Res<GameTime>,
+    cycle: Res<DayNightCycle>,
+    mut ambient: ResMut<AmbientLight>,
+) {
+    let stamp = game_time.get_stamp();
+    let hour = stamp.hour as f32
+        + stamp.minute as f32 / 60.0
+        + stamp.second as f32 / 3600.0;
+    let tint = cycle.tint_at(hour);
+    // Only marked as changed when the light does, which sprites wait on
+    if ambient.tint != tint {
+        *ambient = AmbientLight::from_tint(tint);
+    }
+}
+
+pub fn apply_ambient_light(
+    ambient: Res<AmbientLight>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    overlays: Query<&Handle<ColorMaterial>, With<NightOverlay>>,
+    mut sprites: QuerySet<(
+        Query<(&mut TextureAtlasSprite, &BaseColor)>,
+        Query<(&mut TextureAtlasSprite, &BaseColor), Changed<BaseColor>>,
+    )>,
+    mut lights: Query<(&LightSource, &Handle<ColorMaterial>, &mut Visible)>,
+) {
+    if !ambient.is_changed() {
+        // Same light as last frame, only new or recoloured sprites
+        for (mut sprite, base) in sprites.q1_mut().iter_mut() {
+            sprite.color = modulate(base.0, ambient.tint);
+        }
+        return;
+    }
+    for handle in overlays.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color.set_a(ambient.darkness * MAX_OVERLAY_ALPHA);
+        }
+    }
+    for (mut sprite, base) in sprites.q0_mut().iter_mut() {
+        sprite.color = modulate(base.0, ambient.tint);
+    }
+    for (light, handle, mut visible) in lights.iter_mut() {
+        let intensity = light.intensity(ambient.darkness);
+        visible.is_visible = intensity > 0.0;
+        if let Some(material) = materials.get_mut(handle) {
+            material.color.set_a(light.color.a() * intensity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tint_interpolates_and_wraps_midnight() {
+        let cycle = DayNightCycle::new(vec![
+            (6.0, Color::rgb(0.0, 0.0, 0.0)),
+            (12.0, Color::rgb(1.0, 1.0, 1.0)),
+            (20.0, Color::rgb(0.2, 0.2, 0.2)),
+        ]);
+        assert!((cycle.tint_at(9.0).r() - 0.5).abs() < 1e-5);
+        assert!((cycle.tint_at(12.0).g() - 1.0).abs() < 1e-5);
+        // 20:00 to 06:00 spans midnight, 01:00 is half way
+        assert!((cycle.tint_at(1.0).b() - 0.1).abs() < 1e-5);
+        assert!((cycle.tint_at(25.0).b() - 0.1).abs() < 1e-5);
+    }
+
+    #[test]
+    fn lights_switch_on_after_dark() {
+        let cycle = DayNightCycle::default();
+        let lamp = LightSource::default();
+        let noon = AmbientLight::from_tint(cycle.tint_at(12.0));
+        let midnight = AmbientLight::from_tint(cycle.tint_at(0.0));
+        assert_eq!(lamp.intensity(noon.darkness), 0.0);
+        assert!(lamp.intensity(midnight.darkness) > 0.5);
+    }
+
+    #[test]
+    fn tint_multiplies_the_base_color() {
+        let red = Color::rgba(1.0, 0.2, 0.2, 0.5);
+        let night = Color::rgb(0.25, 0.3, 0.5);
+        let lit = modulate(red, night);
+        assert!((lit.r() - 0.25).abs() < 1e-5);
+        assert!((lit.g() - 0.06).abs() < 1e-5);
+        assert!((lit.b() - 0.1).abs() < 1e-5);
+        assert_eq!(lit.a(), 0.5);
+        assert_eq!(modulate(red, Color::WHITE), red);
+    }
+
+    #[test]
+    fn lamps_line_the_kerbs() {
+        // Pavement either side of a three tile street, grass beyond
+        let mut weight_map = TileWeightMap::new(9, 30);
+        for y in 0..30 {
+            for x in 0..9 {
+                let ground = match x {
+                    0 | 8 => GroundType::ShortGrass,
+                    3..=5 => GroundType::Street,
+                    _ => GroundType::Sidewalk,
+                };
+                weight_map.set(x, y, ground.weight());
+            }
+        }
+        let lamps = kerbside_lamps(&weight_map, 8);
+        assert!(lamps.len() >= 4);
+        for (index, lamp) in lamps.iter().enumerate() {
+            assert!(lamp.x == 2 || lamp.x == 6);
+            for other in &lamps[index + 1..] {
+                assert!(lamp.distance_squared(other) >= 64);
+            }
+        }
+    }
+}
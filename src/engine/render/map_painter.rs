@@ -0,0 +1,79 @@
+// Debug tool for painting ground types onto the map with the mouse.
+// F2 toggles the painter, number keys pick the ground type and holding the
+// left mouse button paints the tile under the cursor. Tiles that already
+// have the chosen ground are left alone, so holding still sends no edits.
+
+use bevy::prelude::*;
+
+use crate::engine::render::{cursor_to_world, MainCamera, Projection};
+use crate::engine::world::{edit::TileEdit, GroundType, TileWeightMap};
+
+pub struct MapPainter {
+    pub enabled: bool,
+    pub ground:  GroundType,
+}
+impl Default for MapPainter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ground:  GroundType::Obstacle,
+        }
+    }
+}
+
+const GROUND_KEYS: [(KeyCode, GroundType); 7] = [
+    (KeyCode::Key1, GroundType::ShortGrass),
+    (KeyCode::Key2, GroundType::TallGrass),
+    (KeyCode::Key3, GroundType::Sidewalk),
+    (KeyCode::Key4, GroundType::Path),
+    (KeyCode::Key5, GroundType::Street),
+    (KeyCode::Key6, GroundType::Crosswalk),
+    (KeyCode::Key7, GroundType::Obstacle),
+];
+
+pub fn paint_tiles(
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    projection: Res<Projection>,
+    weight_map: Res<TileWeightMap>,
+    mut painter: ResMut<MapPainter>,
+    mut edits: EventWriter<TileEdit>,
+    cameras: Query<&Transform, With<MainCamera>>,
+) {
+    if keyboard.just_pressed(KeyCode::F2) {
+        painter.enabled = !painter.enabled;
+        info!("Map painter {}", if painter.enabled { "on" } else { "off" });
+    }
+    if !painter.enabled {
+        return;
+    }
+    for (key, ground) in GROUND_KEYS.iter() {
+        if keyboard.just_pressed(*key) {
+            painter.ground = *ground;
+            info!("Painting {:?}", ground);
+        }
+    }
+    if !mouse.pressed(MouseButton::Left) {
+        return;
+    }
+    if let (Some(window), Some(camera_transform)) =
+        (windows.get_primary(), cameras.iter().next())
+    {
+        if let Some(world_position) = cursor_to_world(window, camera_transform)
+        {
+            let position = projection.to_tile(world_position);
+            // Ground types all weigh differently, so an equal weight means
+            // the tile has this ground already
+            if weight_map.contains(position.x, position.y)
+                && weight_map.get(position.x, position.y)
+                    != painter.ground.weight()
+            {
+                edits.send(TileEdit {
+                    position,
+                    ground: painter.ground,
+                });
+            }
+        }
+    }
+}
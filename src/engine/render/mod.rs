@@ -5,8 +5,26 @@ pub(crate) use bevy::prelude::*;
 use bevy::render::draw::OutsideFrustum;
 
 use crate::engine::actor;
+use crate::engine::bindings::{KeyBindings, BINDINGS_PATH};
 use crate::engine::world;
-mod camera_movement;
+pub mod animation;
+pub mod bubbles;
+pub mod camera_movement;
+pub mod heatmap;
+pub mod layers;
+pub mod lighting;
+mod map_painter;
+pub mod motion;
+pub mod path_overlay;
+pub mod picking;
+mod player_controls;
+pub mod projection;
+mod tilemap;
+
+pub use projection::Projection;
+
+// Marks the camera looking at the world, as opposed to the UI camera
+pub struct MainCamera;
 
 pub struct GraphicsPlugin;
 
@@ -15,43 +33,143 @@ impl Plugin for GraphicsPlugin {
         &self,
         app: &mut AppBuilder,
     ) {
-        app.add_system(animate_sprite_system.system().label("render"))
-            .add_system(camera_movement::camera_movement.system());
+        app.init_resource::<Projection>()
+            .add_startup_system(spawn_cameras.system())
+            .add_startup_system(tilemap::init_tilemaps.system())
+            .add_system(tilemap::retexture_tiles.system())
+            .init_resource::<lighting::DayNightCycle>()
+            .init_resource::<lighting::AmbientLight>()
+            .add_startup_system(lighting::spawn_night_overlay.system())
+            .add_system(
+                lighting::update_ambient_light
+                    .system()
+                    .label("lighting")
+                    .after("time"),
+            )
+            .add_system(
+                lighting::apply_ambient_light
+                    .system()
+                    .label("render")
+                    .after("lighting"),
+            )
+            .init_resource::<animation::SpriteSheets>()
+            .add_system(
+                animate_sprite_system
+                    .system()
+                    .label("render")
+                    .after("action"),
+            )
+            .add_system(
+                motion::place_sprites
+                    .system()
+                    .label("render")
+                    .after("action"),
+            )
+            .insert_resource(KeyBindings::load_or_default(BINDINGS_PATH))
+            .init_resource::<camera_movement::CameraControl>()
+            .add_system(camera_movement::camera_controls.system())
+            .add_system(player_controls::player_keys.system())
+            .add_system(player_controls::click_to_move.system())
+            .add_system(
+                camera_movement::camera_movement
+                    .system()
+                    .label("camera")
+                    .after("render")
+                    .after("picking"),
+            )
+            .insert_resource(map_painter::MapPainter::default())
+            // Edits land in the same frame, before the painter looks again
+            .add_system(map_painter::paint_tiles.system().before("preparation"))
+            .init_resource::<picking::SelectedTile>()
+            .add_startup_system(picking::spawn_highlight.system())
+            .add_system(picking::pick_on_click.system().label("picking"))
+            .add_system(
+                picking::follow_selection
+                    .system()
+                    .label("render")
+                    .after("picking")
+                    .after("action"),
+            )
+            .init_resource::<path_overlay::PathOverlay>()
+            .init_resource::<path_overlay::OverlayMaterials>()
+            .init_resource::<path_overlay::OverlaySprites>()
+            .add_system(path_overlay::path_overlay_controls.system())
+            .add_system(
+                path_overlay::draw_path_overlay.system().after("action"),
+            )
+            .add_system(
+                path_overlay::draw_expanded_nodes.system().after("action"),
+            )
+            .add_system(
+                path_overlay::tint_path_problems
+                    .system()
+                    .after("action")
+                    .before("render"),
+            )
+            .add_system(bubbles::spawn_bubbles.system().after("action"))
+            .add_system(
+                bubbles::update_bubbles
+                    .system()
+                    .after("render")
+                    .after("camera"),
+            )
+            .init_resource::<heatmap::Heatmap>()
+            .add_system(heatmap::update_heatmap.system().after("action"))
+            .add_system(heatmap::export_traffic.system());
     }
 }
 
-const TILE_WIDTH: f32 = 64.0;
+fn spawn_cameras(mut commands: Commands) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera);
+    // Draws the widgets of ui over the world
+    commands.spawn_bundle(UiCameraBundle::default());
+}
+
+pub fn cursor_to_world(
+    window: &Window,
+    camera_transform: &Transform,
+) -> Option<Vec2> {
+    // Window coordinates have their origin in the bottom left corner, the
+    // camera looks at the centre of the window.
+    let cursor = window.cursor_position()?;
+    let size = Vec2::new(window.width(), window.height());
+    let world_position = camera_transform.compute_matrix()
+        * (cursor - size / 2.0).extend(0.0).extend(1.0);
+    Some(Vec2::new(world_position.x, world_position.y))
+}
 
 fn animate_sprite_system(
-    mut query: Query<(
-        &mut TextureAtlasSprite,
-        &mut Transform,
-        &actor::Orientation,
-        &world::Position,
+    game_time: Res<world::time::GameTime>,
+    sub_second: Res<world::time::SubSecond>,
+    sheets: Res<animation::SpriteSheets>,
+    mut query: Query<
+        (
+            &mut TextureAtlasSprite,
+            &mut animation::AnimationState,
+            &Handle<TextureAtlas>,
+            &actor::Orientation,
+            Option<&actor::Path>,
+        ),
         Without<OutsideFrustum>,
-    )>
+    >,
 ) {
-    for (mut sprite, mut transform, orientation, position, _) in
-        &mut query.iter_mut()
-    {
-        // Set sprite to match orientation
-        match orientation.0 {
-            actor::Direction::Up => sprite.index = 5,
-            actor::Direction::Down => sprite.index = 1,
-            actor::Direction::Left => sprite.index = 10,
-            actor::Direction::Right => sprite.index = 13,
-            actor::Direction::UpLeft => todo!(),
-            actor::Direction::UpRight => todo!(),
-            actor::Direction::DownLeft => todo!(),
-            actor::Direction::DownRight => todo!(),
+    for (mut sprite, mut state, atlas, orientation, path) in query.iter_mut() {
+        // Walk while there is a path to follow, the cycle restarting each time
+        // the actor sets off
+        state.walking_since = match (path, state.walking_since) {
+            (Some(_), Some(since)) => Some(since),
+            (Some(_), None) => Some(*game_time),
+            (None, _) => None,
+        };
+        if let Some(meta) = sheets.meta(atlas) {
+            let walked = state.walking_since.map(|since| {
+                game_time.saturating_duration_since(since).as_seconds() as f32
+                    + sub_second.0
+            });
+            sprite.index = meta.frame(orientation.0, walked);
         }
-        // Move sprite to match position
-        let translation = Vec3::new(
-            (position.x as f32).mul_add(TILE_WIDTH, TILE_WIDTH / 2.0),
-            (position.y as f32).mul_add(TILE_WIDTH, TILE_WIDTH / 2.0),
-            1.0, // Layer
-        );
-        transform.translation = translation;
     }
 }
 
@@ -59,19 +177,24 @@ pub fn init_sprite_sheet(
     path: &str,
     asset_server: &Res<AssetServer>,
     texture_atlases: &mut ResMut<Assets<TextureAtlas>>,
+    sheets: &mut ResMut<animation::SpriteSheets>,
     position: world::Position,
+    projection: &Projection,
 ) -> SpriteSheetBundle {
-    let texture_handle = asset_server.load(path);
-    let texture_atlas =
-        TextureAtlas::from_grid(texture_handle, Vec2::new(4.0, 4.0), 4, 4);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    let texture_atlas_handle = sheets
+        .atlas(path, asset_server, texture_atlases)
+        .unwrap_or_else(|| panic!("No metadata for sprite sheet {}", path));
+    let frame_size = sheets
+        .meta(&texture_atlas_handle)
+        .map_or(Vec2::ONE, |meta| meta.frame_size);
 
-    let translation = Vec3::new(
-        (position.x as f32).mul_add(TILE_WIDTH, TILE_WIDTH / 2.0),
-        (position.y as f32).mul_add(TILE_WIDTH, TILE_WIDTH / 2.0),
-        -1.0, // Layer
-    );
-    let mut transform = Transform::from_scale(Vec3::splat(TILE_WIDTH / 3.0));
+    // Scaled so one frame spans a tile
+    let translation = projection
+        .to_world(&position)
+        .extend(layers::RenderLayer::Actors.z());
+    let mut transform = Transform::from_scale(Vec3::splat(
+        projection.tile_size().x / frame_size.x,
+    ));
     transform.translation = translation;
     SpriteSheetBundle {
         texture_atlas: texture_atlas_handle,
@@ -0,0 +1,112 @@
+// Motion:
+// The simulation moves actors a whole tile at a time. Sprites instead slide
+// from the tile of their LastStep to their Position, starting at the exact
+// game time of the step, SubSecond included, and taking as long as the step
+// before it did, so the motion stays smooth at any GameTimeRate.
+
+use bevy::{prelude::*, render::draw::OutsideFrustum};
+
+use crate::engine::actor::LastStep;
+use crate::engine::render::{layers::RenderLayer, Projection};
+use crate::engine::world::time::{GameTime, SubSecond};
+use crate::engine::world::{Position, TileWeightMap};
+
+pub fn step_progress(
+    last_step: &LastStep,
+    now: GameTime,
+    sub_second: f32,
+) -> f32 {
+    // 0 on the tile stepped from, 1 on arrival
+    if last_step.duration <= 0.0 {
+        return 1.0;
+    }
+    let elapsed = last_step.elapsed(now, sub_second);
+    (elapsed / last_step.duration).clamp(0.0, 1.0)
+}
+
+pub fn interpolate(
+    from: Vec2,
+    to: Vec2,
+    from_tile: &Position,
+    to_tile: &Position,
+    progress: f32,
+) -> Vec2 {
+    // Anything further than a diagonal step was a teleport, which snaps
+    if from_tile.distance_squared(to_tile) > 2 {
+        return to;
+    }
+    from + (to - from) * progress
+}
+
+pub fn place_sprites(
+    projection: Res<Projection>,
+    weight_map: Res<TileWeightMap>,
+    game_time: Res<GameTime>,
+    sub_second: Res<SubSecond>,
+    mut query: Query<
+        (&mut Transform, &Position, Option<&LastStep>),
+        (With<TextureAtlasSprite>, Without<OutsideFrustum>),
+    >,
+) {
+    let (width, height) = (weight_map.width(), weight_map.height());
+    for (mut transform, position, last_step) in query.iter_mut() {
+        let (from, progress) = match last_step {
+            Some(last_step) => (
+                last_step.from,
+                step_progress(last_step, *game_time, sub_second.0),
+            ),
+            None => (*position, 1.0),
+        };
+        let translation = interpolate(
+            projection.to_world(&from),
+            projection.to_world(position),
+            &from,
+            position,
+            progress,
+        );
+        // Sorted by the sprite's own height, lower on screen drawn in front
+        let depth = projection.depth_at(translation.y, width, height);
+        transform.translation =
+            translation.extend(RenderLayer::Actors.sorted(depth));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::time::{GameDuration, Stamp};
+
+    #[test]
+    fn slides_over_the_step() {
+        let at = GameTime::from_stamp(&Stamp {
+            day:    0,
+            hour:   6,
+            minute: 0,
+            second: 0,
+        });
+        // Stepped a quarter into the second, after a step of half a second
+        let last_step = LastStep {
+            from: Position { x: 0, y: 0 },
+            at,
+            sub_second: 0.25,
+            duration: 0.5,
+        };
+        assert_eq!(step_progress(&last_step, at, 0.25), 0.0);
+        assert_eq!(step_progress(&last_step, at, 0.5), 0.5);
+        let next = at + GameDuration::from_seconds(1);
+        assert_eq!(step_progress(&last_step, next, 0.0), 1.0);
+        let standing = LastStep {
+            duration: 0.0,
+            ..last_step
+        };
+        assert_eq!(step_progress(&standing, at, 0.25), 1.0);
+        let (from, to) = (Position { x: 0, y: 0 }, Position { x: 1, y: 0 });
+        let (start, end) = (Vec2::new(0.0, 0.0), Vec2::new(64.0, 32.0));
+        assert_eq!(
+            interpolate(start, end, &from, &to, 0.5),
+            Vec2::new(32.0, 16.0)
+        );
+        let far = Position { x: 5, y: 0 };
+        assert_eq!(interpolate(start, end, &from, &far, 0.5), end);
+    }
+}
@@ -0,0 +1,312 @@
+// Path overlay:
+// Debug drawing for pathfinding, toggled with F4. Each actor's remaining Path
+// is drawn as a polyline starting at its position and its Destination is
+// marked; actors whose last plan failed are tinted red, those whose path
+// local_avoidance collapsed to their own tile orange. F5 also shows the tiles
+// aStar expands when planning the Selected actor's route.
+//
+// The sprites are kept from frame to frame: an actor's are only moved, added
+// or removed when its Path or Destination changes, and the expanded tiles are
+// traced again only when F5 is toggled or the selection or its Destination
+// changes.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::engine::actor::{trace_path, Path, PathCollapsed, PathFailed};
+use crate::engine::render::{layers::RenderLayer, lighting::BaseColor,
+                            Projection};
+use crate::engine::world::{Destination, Position, TileWeightMap};
+use crate::engine::Selected;
+
+const LINE_WIDTH: f32 = 6.0;
+const FAILED_TINT: Color = Color::rgb(1.0, 0.2, 0.2);
+const COLLAPSED_TINT: Color = Color::rgb(1.0, 0.6, 0.1);
+
+#[derive(Default)]
+pub struct PathOverlay {
+    pub enabled:       bool,
+    pub show_expanded: bool,
+}
+
+pub struct OverlaySprite;
+
+// The overlay sprites currently spawned
+#[derive(Default)]
+pub struct OverlaySprites {
+    actors:   HashMap<Entity, Vec<Entity>>, // Destination marker, then path
+    expanded: Vec<Entity>,
+}
+
+pub struct OverlayMaterials {
+    path:        Handle<ColorMaterial>,
+    destination: Handle<ColorMaterial>,
+    expanded:    Handle<ColorMaterial>,
+}
+impl FromWorld for OverlayMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials =
+            world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+        Self {
+            path:        materials.add(Color::rgba(0.2, 0.8, 1.0, 0.7).into()),
+            destination: materials.add(Color::rgba(1.0, 0.2, 0.8, 0.8).into()),
+            expanded:    materials.add(Color::rgba(1.0, 1.0, 0.3, 0.25).into()),
+        }
+    }
+}
+
+pub fn segment(
+    from: Vec2,
+    to: Vec2,
+) -> (Vec2, f32, f32) {
+    // Centre, length and angle of the line between two points, for a sprite
+    // stretched along the x axis and then rotated
+    let delta = to - from;
+    ((from + to) / 2.0, delta.length(), delta.y.atan2(delta.x))
+}
+
+fn actor_marks(
+    position: &Position,
+    destination: &Destination,
+    path: Option<&Path>,
+    projection: &Projection,
+) -> Vec<(Vec2, Vec2, f32)> {
+    // Centre, size and angle of the destination marker, then of each segment
+    let mut marks = vec![(
+        projection.to_world(&destination.0),
+        projection.tile_size() * 0.4,
+        std::f32::consts::FRAC_PI_4,
+    )];
+    if let Some(path) = path {
+        let points: Vec<Vec2> = std::iter::once(position)
+            .chain(path.0.iter())
+            .map(|step| projection.to_world(step))
+            .collect();
+        for pair in points.windows(2) {
+            let (centre, length, angle) = segment(pair[0], pair[1]);
+            marks.push((centre, Vec2::new(length, LINE_WIDTH), angle));
+        }
+    }
+    marks
+}
+
+fn overlay_transform(
+    centre: Vec2,
+    angle: f32,
+) -> Transform {
+    Transform {
+        translation: centre.extend(RenderLayer::Overlays.z()),
+        rotation: Quat::from_rotation_z(angle),
+        ..Default::default()
+    }
+}
+
+fn spawn_overlay_sprite(
+    commands: &mut Commands,
+    material: &Handle<ColorMaterial>,
+    centre: Vec2,
+    size: Vec2,
+    angle: f32,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: material.clone(),
+            sprite: Sprite::new(size),
+            transform: overlay_transform(centre, angle),
+            visible: Visible {
+                is_visible:     true,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(OverlaySprite)
+        .id()
+}
+
+pub fn path_overlay_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut overlay: ResMut<PathOverlay>,
+) {
+    if keyboard.just_pressed(KeyCode::F4) {
+        overlay.enabled = !overlay.enabled;
+        info!(
+            "Path overlay {}",
+            if overlay.enabled { "on" } else { "off" }
+        );
+    }
+    if keyboard.just_pressed(KeyCode::F5) {
+        overlay.show_expanded = !overlay.show_expanded;
+    }
+}
+
+pub fn draw_path_overlay(
+    mut commands: Commands,
+    overlay: Res<PathOverlay>,
+    mut sprites: ResMut<OverlaySprites>,
+    materials: Res<OverlayMaterials>,
+    projection: Res<Projection>,
+    mut drawn: Query<(&mut Transform, &mut Sprite), With<OverlaySprite>>,
+    actors: Query<(Entity, &Position, &Destination, Option<&Path>)>,
+    changed: Query<
+        Entity,
+        (With<Destination>, Or<(Changed<Destination>, Changed<Path>)>),
+    >,
+    removed_paths: RemovedComponents<Path>,
+    removed_destinations: RemovedComponents<Destination>,
+) {
+    if !overlay.enabled {
+        if overlay.is_changed() {
+            for (_, owned) in sprites.actors.drain() {
+                for sprite in owned {
+                    commands.entity(sprite).despawn();
+                }
+            }
+        }
+        return;
+    }
+    // Everyone when the overlay is switched on, else only what changed
+    let redraw: HashSet<Entity> = if overlay.is_changed() {
+        actors.iter().map(|(actor, ..)| actor).collect()
+    } else {
+        changed
+            .iter()
+            .chain(removed_paths.iter())
+            .chain(removed_destinations.iter())
+            .collect()
+    };
+    for actor in redraw {
+        let marks = match actors.get(actor) {
+            Ok((_, position, destination, path)) => {
+                actor_marks(position, destination, path, &projection)
+            }
+            Err(_) => Vec::new(), // Arrived, or gone
+        };
+        let owned = sprites.actors.entry(actor).or_default();
+        // Moves the sprites already there, spawning or despawning the rest
+        while owned.len() > marks.len() {
+            commands.entity(owned.pop().unwrap()).despawn();
+        }
+        for (index, (centre, size, angle)) in marks.into_iter().enumerate() {
+            match owned.get(index) {
+                Some(sprite) => {
+                    if let Ok((mut transform, mut sprite)) =
+                        drawn.get_mut(*sprite)
+                    {
+                        *transform = overlay_transform(centre, angle);
+                        sprite.size = size;
+                    }
+                }
+                None => {
+                    let material = if index == 0 {
+                        &materials.destination
+                    } else {
+                        &materials.path
+                    };
+                    owned.push(spawn_overlay_sprite(
+                        &mut commands,
+                        material,
+                        centre,
+                        size,
+                        angle,
+                    ));
+                }
+            }
+        }
+        if owned.is_empty() {
+            sprites.actors.remove(&actor);
+        }
+    }
+}
+
+pub fn draw_expanded_nodes(
+    mut commands: Commands,
+    overlay: Res<PathOverlay>,
+    mut sprites: ResMut<OverlaySprites>,
+    materials: Res<OverlayMaterials>,
+    projection: Res<Projection>,
+    weight_map: Res<TileWeightMap>,
+    selected: Query<(&Position, Option<&Destination>), With<Selected>>,
+    changed: Query<
+        Entity,
+        (With<Selected>, Or<(Added<Selected>, Changed<Destination>)>),
+    >,
+    removed_destinations: RemovedComponents<Destination>,
+    deselected: RemovedComponents<Selected>,
+) {
+    // A full aStar run, so only traced again when its inputs change
+    let stale = overlay.is_changed()
+        || changed.iter().next().is_some()
+        || deselected.iter().next().is_some()
+        || removed_destinations
+            .iter()
+            .any(|actor| selected.get(actor).is_ok());
+    if !stale {
+        return;
+    }
+    for sprite in sprites.expanded.drain(..) {
+        commands.entity(sprite).despawn();
+    }
+    if !(overlay.enabled && overlay.show_expanded) {
+        return;
+    }
+    let tile_size = projection.tile_size();
+    for (position, destination) in selected.iter() {
+        let destination = match destination {
+            Some(destination) => destination,
+            None => continue,
+        };
+        let mut expanded = Vec::new();
+        trace_path(position, &destination.0, &weight_map, &mut expanded);
+        for tile in expanded {
+            let sprite = spawn_overlay_sprite(
+                &mut commands,
+                &materials.expanded,
+                projection.to_world(&tile),
+                tile_size * 0.8,
+                0.0,
+            );
+            sprites.expanded.push(sprite);
+        }
+    }
+}
+
+pub fn tint_path_problems(
+    overlay: Res<PathOverlay>,
+    mut actors: Query<(
+        &mut BaseColor,
+        Option<&PathFailed>,
+        Option<&PathCollapsed>,
+    )>,
+) {
+    // Sets the base color, so the ambient tint still applies on top; written
+    // only when it differs, as a change recolours the sprite
+    for (mut base, failed, collapsed) in actors.iter_mut() {
+        let color = match (overlay.enabled, failed, collapsed) {
+            (true, Some(_), _) => FAILED_TINT,
+            (true, None, Some(_)) => COLLAPSED_TINT,
+            _ => Color::WHITE,
+        };
+        if base.0 != color {
+            base.0 = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_span_their_endpoints() {
+        let (centre, length, angle) =
+            segment(Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0));
+        assert_eq!(centre, Vec2::new(5.0, 0.0));
+        assert!((length - 10.0).abs() < 1e-5 && angle.abs() < 1e-5);
+        let (centre, length, angle) =
+            segment(Vec2::new(2.0, 2.0), Vec2::new(2.0, -4.0));
+        assert_eq!(centre, Vec2::new(2.0, -1.0));
+        assert!((length - 6.0).abs() < 1e-5);
+        assert!((angle + std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,135 @@
+// Mouse picking:
+// A left click converts the cursor through the main camera and the projection
+// to a tile, selects the actor standing there (or whatever else occupies the
+// tile) and remembers the tile itself in SelectedTile. The Selected component
+// is what other tools read; a highlight sprite follows the selection.
+
+use bevy::prelude::*;
+
+use crate::engine::render::map_painter::MapPainter;
+use crate::engine::render::{cursor_to_world, layers::RenderLayer, MainCamera,
+                            Projection};
+use crate::engine::world::{Position, TileEntityMap, TileWeightMap};
+use crate::engine::Selected;
+
+const HIGHLIGHT_OFFSET: f32 = 0.5; // Above the heatmap
+
+#[derive(Default)]
+pub struct SelectedTile(pub Option<Position>);
+
+pub struct SelectionHighlight;
+
+pub fn pick_occupant(
+    entity_map: &TileEntityMap,
+    position: &Position,
+) -> Option<Entity> {
+    // Blocking occupants (actors) win over items sharing the tile
+    entity_map
+        .occupants(position.x, position.y)
+        .and_then(|occupants| occupants.iter().next())
+}
+
+pub fn pick_on_click(
+    mut commands: Commands,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    projection: Res<Projection>,
+    painter: Res<MapPainter>,
+    entity_map: Res<TileEntityMap>,
+    weight_map: Res<TileWeightMap>,
+    mut selected_tile: ResMut<SelectedTile>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    interactions: Query<&Interaction>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    // Clicks belong to the painter while it is on, and to the UI over nodes
+    if !mouse.just_pressed(MouseButton::Left)
+        || painter.enabled
+        || interactions
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let world_position = match (windows.get_primary(), cameras.iter().next()) {
+        (Some(window), Some(camera_transform)) => {
+            cursor_to_world(window, camera_transform)
+        }
+        _ => None,
+    };
+    let tile = match world_position {
+        Some(world_position) => projection.to_tile(world_position),
+        None => return,
+    };
+    for entity in selected.iter() {
+        commands.entity(entity).remove::<Selected>();
+    }
+    if let Some(entity) = pick_occupant(&entity_map, &tile) {
+        commands.entity(entity).insert(Selected);
+    }
+    selected_tile.0 = if weight_map.contains(tile.x, tile.y) {
+        Some(tile)
+    } else {
+        None
+    };
+}
+
+pub fn spawn_highlight(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    projection: Res<Projection>,
+) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            material: materials.add(Color::rgba(1.0, 0.9, 0.2, 0.4).into()),
+            sprite: Sprite::new(projection.tile_size()),
+            visible: Visible {
+                is_visible:     false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(SelectionHighlight);
+}
+
+pub fn follow_selection(
+    projection: Res<Projection>,
+    selected_tile: Res<SelectedTile>,
+    selected: Query<&Position, With<Selected>>,
+    mut highlights: Query<
+        (&mut Transform, &mut Visible),
+        With<SelectionHighlight>,
+    >,
+) {
+    // The selected actor when there is one, otherwise the clicked tile
+    let target = selected.iter().next().copied().or(selected_tile.0);
+    for (mut transform, mut visible) in highlights.iter_mut() {
+        visible.is_visible = target.is_some();
+        if let Some(position) = target {
+            transform.translation = projection
+                .to_world(&position)
+                .extend(RenderLayer::Decals.z() + HIGHLIGHT_OFFSET);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::Occupancy;
+
+    #[test]
+    fn picks_actor_over_items() {
+        let mut entity_map = TileEntityMap::new(4, 4);
+        let (item, actor) = (Entity::new(1), Entity::new(2));
+        let tile = Position { x: 1, y: 2 };
+        entity_map
+            .place(item, tile, Occupancy::NonBlocking)
+            .unwrap();
+        assert_eq!(pick_occupant(&entity_map, &tile), Some(item));
+        entity_map.place(actor, tile, Occupancy::Blocking).unwrap();
+        assert_eq!(pick_occupant(&entity_map, &tile), Some(actor));
+        assert_eq!(pick_occupant(&entity_map, &Position { x: 0, y: 0 }), None);
+        assert_eq!(pick_occupant(&entity_map, &Position { x: 9, y: 0 }), None);
+    }
+}
@@ -0,0 +1,87 @@
+// Player controls:
+// Turns the windowed input into PlayerOrders. The move keys from KeyBindings
+// step the player one tile, diagonally when two are held: once when pressed,
+// then every STEP_INTERVAL of game time for as long as they are held, the
+// pace every actor steps at. Right clicking a tile walks the player there,
+// unless the inspector has the right button.
+
+use bevy::prelude::*;
+
+use crate::engine::actor::{player::PlayerOrder, STEP_INTERVAL};
+use crate::engine::bindings::{Control, KeyBindings};
+use crate::engine::render::{cursor_to_world, MainCamera, Projection};
+use crate::engine::ui::inspector::Inspector;
+use crate::engine::world::{time::GameTime, Position};
+
+const MOVES: [Control; 4] = [
+    Control::MoveLeft,
+    Control::MoveRight,
+    Control::MoveUp,
+    Control::MoveDown,
+];
+
+pub fn player_keys(
+    keyboard: Res<Input<KeyCode>>,
+    bindings: Res<KeyBindings>,
+    game_time: Res<GameTime>,
+    mut last_order: Local<Option<GameTime>>,
+    mut orders: EventWriter<PlayerOrder>,
+) {
+    let mut offset = Position { x: 0, y: 0 };
+    if bindings.pressed(Control::MoveLeft, &keyboard) {
+        offset.x -= 1;
+    }
+    if bindings.pressed(Control::MoveRight, &keyboard) {
+        offset.x += 1;
+    }
+    if bindings.pressed(Control::MoveUp, &keyboard) {
+        offset.y += 1;
+    }
+    if bindings.pressed(Control::MoveDown, &keyboard) {
+        offset.y -= 1;
+    }
+    if offset == (Position { x: 0, y: 0 }) {
+        *last_order = None;
+        return;
+    }
+    // A key pressed this frame, e.g. turning a corner, steps at once
+    let pressed = MOVES
+        .iter()
+        .any(|control| bindings.just_pressed(*control, &keyboard));
+    let repeat = last_order.map_or(true, |at| {
+        game_time.saturating_duration_since(at) >= STEP_INTERVAL
+    });
+    if pressed || repeat {
+        orders.send(PlayerOrder::Step(offset));
+        *last_order = Some(*game_time);
+    }
+}
+
+pub fn click_to_move(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    projection: Res<Projection>,
+    inspector: Res<Inspector>,
+    mut orders: EventWriter<PlayerOrder>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    interactions: Query<&Interaction>,
+) {
+    if !mouse.just_pressed(MouseButton::Right)
+        || inspector.visible
+        || interactions
+            .iter()
+            .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let tile = match (windows.get_primary(), cameras.iter().next()) {
+        (Some(window), Some(camera_transform)) => {
+            cursor_to_world(window, camera_transform)
+                .map(|world_position| projection.to_tile(world_position))
+        }
+        _ => None,
+    };
+    if let Some(tile) = tile {
+        orders.send(PlayerOrder::WalkTo(tile));
+    }
+}
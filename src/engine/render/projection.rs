@@ -0,0 +1,203 @@
+// Projection between grid positions and world (screen) space.
+// All sprite placement, camera bounds and mouse picking go through the
+// Projection resource so that the same simulation can be drawn on orthogonal
+// or isometric maps. Tile sizes are in world units, i.e. after the tilemap's
+// scale has been applied. Layouts follow bevy_ecs_tilemap's meshers, with
+// tile (0, 0) at the world origin and y pointing up.
+
+use bevy::prelude::*;
+
+use crate::engine::world::Position;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Projection {
+    Orthogonal { tile_size: Vec2 },
+    // Diamond isometric: x runs to the upper right, y to the upper left
+    IsoDiamond { tile_size: Vec2 },
+    // Staggered isometric: rows are half a tile apart, odd rows shifted right
+    IsoStaggered { tile_size: Vec2 },
+}
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Orthogonal {
+            tile_size: Vec2::splat(64.0),
+        }
+    }
+}
+impl Projection {
+    pub fn tile_size(&self) -> Vec2 {
+        match *self {
+            Projection::Orthogonal { tile_size }
+            | Projection::IsoDiamond { tile_size }
+            | Projection::IsoStaggered { tile_size } => tile_size,
+        }
+    }
+    pub fn is_isometric(&self) -> bool {
+        !matches!(self, Projection::Orthogonal { .. })
+    }
+    pub fn to_world(
+        &self,
+        position: &Position,
+    ) -> Vec2 {
+        // Centre of the tile in world coordinates
+        let (x, y) = (position.x as f32, position.y as f32);
+        let size = self.tile_size();
+        match self {
+            Projection::Orthogonal { .. } => {
+                Vec2::new((x + 0.5) * size.x, (y + 0.5) * size.y)
+            }
+            Projection::IsoDiamond { .. } => Vec2::new(
+                (x - y) * size.x / 2.0 + size.x / 2.0,
+                (x + y) * size.y / 2.0 + size.y / 2.0,
+            ),
+            Projection::IsoStaggered { .. } => {
+                let offset = if position.y.rem_euclid(2) == 1 {
+                    size.x / 2.0
+                } else {
+                    0.0
+                };
+                Vec2::new(
+                    x * size.x + offset + size.x / 2.0,
+                    y * size.y / 2.0 + size.y / 2.0,
+                )
+            }
+        }
+    }
+    pub fn to_tile(
+        &self,
+        world_position: Vec2,
+    ) -> Position {
+        // Inverse of to_world; returns the tile whose footprint contains the
+        // world position
+        let size = self.tile_size();
+        match self {
+            Projection::Orthogonal { .. } => Position {
+                x: (world_position.x / size.x).floor() as i64,
+                y: (world_position.y / size.y).floor() as i64,
+            },
+            Projection::IsoDiamond { .. } => {
+                let u = (world_position.x - size.x / 2.0) / (size.x / 2.0);
+                let v = (world_position.y - size.y / 2.0) / (size.y / 2.0);
+                Position {
+                    x: ((v + u) / 2.0).round() as i64,
+                    y: ((v - u) / 2.0).round() as i64,
+                }
+            }
+            Projection::IsoStaggered { .. } => {
+                // Rows overlap, so test the diamonds of the nearby candidates
+                let row = ((world_position.y - size.y / 2.0) / (size.y / 2.0))
+                    .round() as i64;
+                let column =
+                    ((world_position.x - size.x / 2.0) / size.x).round() as i64;
+                let mut best = Position { x: column, y: row };
+                let mut best_distance = f32::MAX;
+                for y in (row - 1)..=(row + 1) {
+                    for x in (column - 1)..=(column + 1) {
+                        let candidate = Position { x, y };
+                        let delta = world_position - self.to_world(&candidate);
+                        let distance = delta.x.abs() / (size.x / 2.0)
+                            + delta.y.abs() / (size.y / 2.0);
+                        if distance < best_distance {
+                            best = candidate;
+                            best_distance = distance;
+                        }
+                    }
+                }
+                best
+            }
+        }
+    }
+    pub fn depth(
+        &self,
+        position: &Position,
+        width: i64,
+        height: i64,
+    ) -> f32 {
+        // Fraction in [0, 1) that grows towards the viewer, for sorting
+        // sprites within a layer: lower on screen draws in front
+        self.depth_at(self.to_world(position).y, width, height)
+    }
+    pub fn depth_at(
+        &self,
+        world_y: f32,
+        width: i64,
+        height: i64,
+    ) -> f32 {
+        // Depth of any world height, for sprites between tiles
+        let (min, max) = self.map_bounds(width, height);
+        1.0 - ((world_y - min.y) / (max.y - min.y + 1.0)).clamp(0.0, 1.0)
+    }
+    pub fn map_bounds(
+        &self,
+        width: i64,
+        height: i64,
+    ) -> (Vec2, Vec2) {
+        // World-space bounding box (min, max) of a width x height map
+        let size = self.tile_size();
+        let corners = [
+            Position { x: 0, y: 0 },
+            Position { x: width - 1, y: 0 },
+            Position {
+                x: 0,
+                y: height - 1,
+            },
+            Position {
+                x: width - 1,
+                y: height - 1,
+            },
+        ];
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for corner in corners.iter() {
+            let centre = self.to_world(corner);
+            min = min.min(centre - size / 2.0);
+            max = max.max(centre + size / 2.0);
+        }
+        (min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trips(projection: Projection) {
+        for x in -3..12 {
+            for y in -3..12 {
+                let position = Position { x, y };
+                let centre = projection.to_world(&position);
+                assert_eq!(projection.to_tile(centre), position);
+                // Points slightly inside the footprint map to the same tile
+                let nudge = projection.tile_size() * 0.1;
+                assert_eq!(projection.to_tile(centre + nudge), position);
+            }
+        }
+    }
+
+    #[test]
+    fn orthogonal_round_trip() {
+        round_trips(Projection::Orthogonal {
+            tile_size: Vec2::splat(64.0),
+        });
+    }
+
+    #[test]
+    fn isometric_round_trip() {
+        round_trips(Projection::IsoDiamond {
+            tile_size: Vec2::new(64.0, 32.0),
+        });
+        round_trips(Projection::IsoStaggered {
+            tile_size: Vec2::new(64.0, 32.0),
+        });
+    }
+
+    #[test]
+    fn lower_tiles_sort_in_front() {
+        let projection = Projection::IsoDiamond {
+            tile_size: Vec2::new(64.0, 32.0),
+        };
+        let back = projection.depth(&Position { x: 5, y: 5 }, 10, 10);
+        let front = projection.depth(&Position { x: 1, y: 1 }, 10, 10);
+        assert!(front > back);
+    }
+}
@@ -0,0 +1,51 @@
+// Tilemap:
+// Loads the Tiled map with bevy_ecs_tilemap and keeps its ground layer in step
+// with TileEdits, swapping the texture of each edited tile. The weights
+// themselves are world::edit's business.
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::engine::world::{edit::TileEdit, MapSettings};
+
+const MAP_ID: u16 = 0;
+const GROUND_LAYER_ID: u16 = 0;
+
+pub fn init_tilemaps(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<MapSettings>,
+) {
+    let handle: Handle<TiledMap> = asset_server.load(settings.path.as_str());
+
+    let map_entity = commands.spawn().id();
+
+    commands.entity(map_entity).insert_bundle(TiledMapBundle {
+        tiled_map: handle,
+        map: Map::new(MAP_ID, map_entity),
+        transform: Transform::from_xyz(0.0, 0.0, 0.0)
+            .mul_transform(Transform::from_scale(Vec3::splat(4.0))),
+        ..Default::default()
+    });
+}
+
+pub fn retexture_tiles(
+    mut commands: Commands,
+    mut edits: EventReader<TileEdit>,
+    mut map_query: MapQuery,
+) {
+    for edit in edits.iter() {
+        // Tiles outside of the loaded tilemap only exist in the weight map
+        let tile_pos = TilePos(edit.position.x as u32, edit.position.y as u32);
+        let tile = Tile {
+            texture_index: edit.ground.texture_index(),
+            ..Default::default()
+        };
+        if map_query
+            .set_tile(&mut commands, tile_pos, tile, MAP_ID, GROUND_LAYER_ID)
+            .is_ok()
+        {
+            map_query.notify_chunk_for_tile(tile_pos, MAP_ID, GROUND_LAYER_ID);
+        }
+    }
+}
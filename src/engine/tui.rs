@@ -0,0 +1,354 @@
+// Terminal renderer:
+// Draws the simulation into a terminal with crossterm for remote and headless
+// debugging, run with `--tui` in place of the window. Each frame fills the
+// screen with a viewport of TileWeightMap, which WorldPlugin reads from the
+// map file, one character per tile with north up, puts actors on top as arrows
+// showing their Orientation (the player is an @), and keeps a status line with
+// the game time at the bottom. The arrow keys or WASD scroll, F follows the
+// player, space pauses the game clock,
+// + and - change its speed and Q or Escape quits.
+
+use std::io::{stdout, Write};
+use std::time::Duration;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use crossterm::{cursor,
+                event::{self, Event, KeyCode as TermKey, KeyEvent,
+                        KeyModifiers},
+                queue,
+                style::{Color as TermColor, Print, ResetColor,
+                        SetBackgroundColor, SetForegroundColor},
+                terminal};
+
+use crate::engine::actor::player::Player;
+use crate::engine::actor::{Direction, Orientation};
+use crate::engine::world::time::{GameTime, GameTimeRate};
+use crate::engine::world::{GroundType, Position, TileWeightMap};
+
+const SCROLL_STEP: i64 = 1;
+const FAST_SCROLL_STEP: i64 = 10; // With shift held
+const MIN_RATE: f32 = 0.25; // As the clock widget
+const MAX_RATE: f32 = 64.0;
+const UNKNOWN_GROUND: (char, TermColor) = ('·', TermColor::DarkGrey);
+const GROUND_GLYPHS: [(GroundType, char, TermColor); 7] = [
+    (GroundType::ShortGrass, '.', TermColor::Green),
+    (GroundType::TallGrass, '"', TermColor::DarkGreen),
+    (GroundType::Sidewalk, '░', TermColor::Grey),
+    (GroundType::Path, ':', TermColor::DarkYellow),
+    (GroundType::Street, '▒', TermColor::DarkGrey),
+    (GroundType::Crosswalk, '=', TermColor::White),
+    (GroundType::Obstacle, '█', TermColor::DarkGrey),
+];
+
+pub struct TerminalPlugin;
+
+impl Plugin for TerminalPlugin {
+    fn build(
+        &self,
+        app: &mut AppBuilder,
+    ) {
+        app.init_resource::<TerminalView>()
+            .add_startup_system(open_terminal.system())
+            .add_system(terminal_input.system())
+            .add_system(draw_terminal.system().after("action").after("time"));
+    }
+}
+
+// Which tile is at the centre of the terminal
+pub struct TerminalView {
+    pub centre: Option<Position>, // None until the map is known
+    pub follow: bool,             // Keep the player in the centre
+}
+impl Default for TerminalView {
+    fn default() -> Self {
+        Self {
+            centre: None,
+            follow: true,
+        }
+    }
+}
+
+// Puts the terminal back the way it was once the app is dropped
+struct TerminalGuard;
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(
+            stdout(),
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Viewport {
+    pub centre:  Position,
+    pub columns: u16,
+    pub rows:    u16,
+}
+impl Viewport {
+    pub fn tile_at(
+        &self,
+        column: u16,
+        row: u16,
+    ) -> Position {
+        // Row 0 is the top of the screen, the northern edge
+        Position {
+            x: self.centre.x - i64::from(self.columns / 2) + i64::from(column),
+            y: self.centre.y + i64::from(self.rows / 2) - i64::from(row),
+        }
+    }
+    pub fn cell_of(
+        &self,
+        position: &Position,
+    ) -> Option<(u16, u16)> {
+        let column = position.x - self.centre.x + i64::from(self.columns / 2);
+        let row = self.centre.y + i64::from(self.rows / 2) - position.y;
+        if (0..i64::from(self.columns)).contains(&column)
+            && (0..i64::from(self.rows)).contains(&row)
+        {
+            Some((column as u16, row as u16))
+        } else {
+            None
+        }
+    }
+}
+
+pub fn ground_glyph(weight: i64) -> (char, TermColor) {
+    GROUND_GLYPHS
+        .iter()
+        .find(|(ground, ..)| ground.weight() == weight)
+        .map_or(UNKNOWN_GROUND, |(_, glyph, color)| (*glyph, *color))
+}
+
+pub fn actor_glyph(direction: Direction) -> char {
+    match direction {
+        Direction::Up => '↑',
+        Direction::UpLeft => '↖',
+        Direction::UpRight => '↗',
+        Direction::Down => '↓',
+        Direction::DownLeft => '↙',
+        Direction::DownRight => '↘',
+        Direction::Left => '←',
+        Direction::Right => '→',
+    }
+}
+
+fn open_terminal(mut commands: Commands) {
+    terminal::enable_raw_mode()
+        .and_then(|()| {
+            crossterm::execute!(
+                stdout(),
+                terminal::EnterAlternateScreen,
+                cursor::Hide
+            )
+        })
+        .expect("Could not open the terminal");
+    commands.insert_resource(TerminalGuard);
+}
+
+fn terminal_input(
+    mut view: ResMut<TerminalView>,
+    mut rate: ResMut<GameTimeRate>,
+    mut exit: EventWriter<AppExit>,
+) {
+    // Drains whatever was typed since the last frame without waiting
+    while let Ok(true) = event::poll(Duration::ZERO) {
+        let (code, modifiers) = match event::read() {
+            Ok(Event::Key(KeyEvent { code, modifiers })) => (code, modifiers),
+            _ => continue,
+        };
+        let step = if modifiers.contains(KeyModifiers::SHIFT) {
+            FAST_SCROLL_STEP
+        } else {
+            SCROLL_STEP
+        };
+        let scroll = match code {
+            TermKey::Left | TermKey::Char('a') | TermKey::Char('A') => {
+                Position { x: -step, y: 0 }
+            }
+            TermKey::Right | TermKey::Char('d') | TermKey::Char('D') => {
+                Position { x: step, y: 0 }
+            }
+            TermKey::Up | TermKey::Char('w') | TermKey::Char('W') => {
+                Position { x: 0, y: step }
+            }
+            TermKey::Down | TermKey::Char('s') | TermKey::Char('S') => {
+                Position { x: 0, y: -step }
+            }
+            _ => Position { x: 0, y: 0 },
+        };
+        if scroll != (Position { x: 0, y: 0 }) {
+            view.follow = false;
+            if let Some(centre) = &mut view.centre {
+                centre.x += scroll.x;
+                centre.y += scroll.y;
+            }
+        }
+        match code {
+            TermKey::Char('f') | TermKey::Char('F') => {
+                view.follow = !view.follow
+            }
+            TermKey::Char(' ') => rate.toggle_pause(),
+            TermKey::Char('+') | TermKey::Char('=') => {
+                let faster = (rate.rate() * 2.0).min(MAX_RATE);
+                rate.set_rate(faster);
+            }
+            TermKey::Char('-') => {
+                let slower = (rate.rate() / 2.0).max(MIN_RATE);
+                rate.set_rate(slower);
+            }
+            TermKey::Char('q') | TermKey::Char('Q') | TermKey::Esc => {
+                exit.send(AppExit)
+            }
+            TermKey::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                exit.send(AppExit)
+            }
+            _ => (),
+        }
+    }
+}
+
+fn draw_terminal(
+    game_time: Res<GameTime>,
+    rate: Res<GameTimeRate>,
+    weight_map: Res<TileWeightMap>,
+    mut view: ResMut<TerminalView>,
+    actors: Query<(&Position, &Orientation, Option<&Player>)>,
+) {
+    let (columns, rows) = terminal::size().unwrap_or((80, 24));
+    if columns == 0 || rows < 2 {
+        return;
+    }
+    let map_rows = rows - 1; // The last row is the status line
+    if view.follow {
+        if let Some((position, ..)) =
+            actors.iter().find(|(.., player)| player.is_some())
+        {
+            view.centre = Some(*position);
+        }
+    }
+    let centre = *view.centre.get_or_insert(Position {
+        x: weight_map.width() / 2,
+        y: weight_map.height() / 2,
+    });
+    let viewport = Viewport {
+        centre,
+        columns,
+        rows: map_rows,
+    };
+    let mut cells: Vec<(char, TermColor)> = (0..map_rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let tile = viewport.tile_at(column, row);
+            if weight_map.contains(tile.x, tile.y) {
+                ground_glyph(weight_map.get(tile.x, tile.y))
+            } else {
+                (' ', TermColor::Reset)
+            }
+        })
+        .collect();
+    // The player last, so it stays on top
+    let mut placed: Vec<_> = actors.iter().collect();
+    placed.sort_by_key(|(.., player)| player.is_some());
+    for (position, orientation, player) in placed {
+        if let Some((column, row)) = viewport.cell_of(position) {
+            let index =
+                usize::from(row) * usize::from(columns) + usize::from(column);
+            cells[index] = match player {
+                Some(_) => ('@', TermColor::Yellow),
+                None => (actor_glyph(orientation.0), TermColor::Cyan),
+            };
+        }
+    }
+    let stamp = game_time.get_stamp();
+    let speed = if rate.is_paused() {
+        "paused".to_owned()
+    } else {
+        format!("x{}", rate.rate())
+    };
+    let status = format!(
+        " Day {} {:02}:{:02}:{:02} {} | ({}, {}){} | arrows scroll, f follow, \
+         space pause, +/- speed, q quit",
+        stamp.day,
+        stamp.hour,
+        stamp.minute,
+        stamp.second,
+        speed,
+        centre.x,
+        centre.y,
+        if view.follow { " following" } else { "" },
+    );
+    let width = usize::from(columns);
+    let status: String = format!("{:<1$}", status, width)
+        .chars()
+        .take(width)
+        .collect();
+    // Colours are only sent where they change, which keeps frames small
+    let mut out = stdout();
+    let mut color = None;
+    for (index, (glyph, glyph_color)) in cells.into_iter().enumerate() {
+        if index % width == 0 {
+            let _ = queue!(out, cursor::MoveTo(0, (index / width) as u16));
+        }
+        if color != Some(glyph_color) {
+            let _ = queue!(out, SetForegroundColor(glyph_color));
+            color = Some(glyph_color);
+        }
+        let _ = queue!(out, Print(glyph));
+    }
+    let _ = queue!(
+        out,
+        cursor::MoveTo(0, map_rows),
+        SetForegroundColor(TermColor::Black),
+        SetBackgroundColor(TermColor::Grey),
+        Print(status),
+        ResetColor
+    );
+    let _ = out.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::ground::paint_layer;
+
+    #[test]
+    fn viewport_puts_north_up() {
+        let viewport = Viewport {
+            centre:  Position { x: 10, y: 10 },
+            columns: 5,
+            rows:    3,
+        };
+        assert_eq!(viewport.tile_at(0, 0), Position { x: 8, y: 11 });
+        assert_eq!(viewport.tile_at(4, 2), Position { x: 12, y: 9 });
+        assert_eq!(viewport.cell_of(&Position { x: 10, y: 10 }), Some((2, 1)));
+        assert_eq!(viewport.cell_of(&Position { x: 13, y: 10 }), None);
+        for column in 0..5 {
+            for row in 0..3 {
+                let tile = viewport.tile_at(column, row);
+                assert_eq!(viewport.cell_of(&tile), Some((column, row)));
+            }
+        }
+    }
+
+    #[test]
+    fn map_ground_has_glyphs() {
+        // A row of maps/test.tmx: grass, a street with its lane markings and
+        // a crosswalk laid over it
+        let mut weight_map = TileWeightMap::new(7, 2);
+        let ground = vec![vec![964, 964, 789, 750, 753, 964, 964]; 2];
+        paint_layer(&mut weight_map, &ground, 1);
+        let crosswalk = vec![vec![0, 0, 827, 827, 827, 0, 0], vec![0; 7]];
+        paint_layer(&mut weight_map, &crosswalk, 1);
+        let row = |y| -> String {
+            (0..7)
+                .map(|x| ground_glyph(weight_map.get(x, y)).0)
+                .collect()
+        };
+        assert_eq!(row(0), "..▒▒▒..");
+        assert_eq!(row(1), "..===..");
+    }
+}
@@ -0,0 +1,276 @@
+// Clock widget:
+// The analog clock of the Python version, assembled from its face, gear and
+// hand images, in the top right corner with a digital readout below it.
+// Space pauses the game clock, + and - double or halve its speed. It is a
+// plugin of its own, apart from the panels of UIPlugin.
+
+use bevy::prelude::*;
+
+use crate::engine::world::time::{GameTime, GameTimeRate};
+
+const FACE_SIZE: f32 = 371.0; // Size of the source images in pixels
+const CLOCK_SIZE: f32 = 160.0;
+const MIN_RATE: f32 = 0.25;
+const MAX_RATE: f32 = 64.0;
+// (image, size, centre on the face, degrees per game second), as in core.py
+const GEARS: [(&str, f32, (f32, f32), f32); 3] = [
+    ("ui/clock/gear1.png", 50.0, (160.5, 155.0), 10.0),
+    ("ui/clock/gear2.png", 76.0, (185.5, 205.0), 5.0),
+    ("ui/clock/gear3.png", 62.0, (205.5, 175.0), 600.0),
+];
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(
+        &self,
+        app: &mut AppBuilder,
+    ) {
+        app.add_startup_system(spawn_clock.system())
+            .add_system(time_controls.system())
+            .add_system(update_clock.system().after("time"));
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ClockHand {
+    Hour,
+    Minute,
+    Second,
+    Gear(f32),
+}
+impl ClockHand {
+    pub fn angle(
+        self,
+        time: GameTime,
+    ) -> f32 {
+        // Degrees clockwise from twelve o'clock
+        match self {
+            ClockHand::Hour => time.get_hour() % 12.0 * 30.0,
+            ClockHand::Minute => time.get_minute() * 6.0,
+            ClockHand::Second => time.get_second().floor() * 6.0,
+            ClockHand::Gear(speed) => {
+                // Gears turn the other way
+                let seconds = time.time_of_day().as_seconds() as f32;
+                -(seconds * speed % 360.0)
+            }
+        }
+    }
+}
+
+pub struct ClockReadout;
+
+pub fn readout(
+    time: GameTime,
+    rate: &GameTimeRate,
+) -> String {
+    let stamp = time.get_stamp();
+    let hour = match stamp.hour % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    let speed = if rate.is_paused() {
+        "Paused".to_owned()
+    } else {
+        format!("x{}", rate.rate())
+    };
+    format!(
+        "Day {} {:02}:{:02}:{:02} {}\n{}",
+        stamp.day,
+        hour,
+        stamp.minute,
+        stamp.second,
+        if time.is_AM() { "AM" } else { "PM" },
+        speed
+    )
+}
+
+fn image(
+    asset_server: &AssetServer,
+    materials: &mut Assets<ColorMaterial>,
+    path: &str,
+    size: f32,
+    centre: (f32, f32),
+) -> ImageBundle {
+    // Places a source image by its centre in face pixel coordinates
+    let scale = CLOCK_SIZE / FACE_SIZE;
+    ImageBundle {
+        style: Style {
+            size: Size::new(Val::Px(size * scale), Val::Px(size * scale)),
+            position_type: PositionType::Absolute,
+            position: Rect {
+                left: Val::Px((centre.0 - size / 2.0) * scale),
+                top: Val::Px((centre.1 - size / 2.0) * scale),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        material: materials.add(asset_server.load(path).into()),
+        ..Default::default()
+    }
+}
+
+pub fn spawn_clock(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let centre = (FACE_SIZE / 2.0, FACE_SIZE / 2.0);
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::NONE.into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(
+                            Val::Px(CLOCK_SIZE),
+                            Val::Px(CLOCK_SIZE),
+                        ),
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::NONE.into()),
+                    ..Default::default()
+                })
+                .with_children(|face| {
+                    // Back to front, as the Python canvas drew them
+                    face.spawn_bundle(image(
+                        &asset_server,
+                        &mut materials,
+                        "ui/clock/face.png",
+                        FACE_SIZE,
+                        centre,
+                    ));
+                    for (path, size, position, speed) in GEARS.iter() {
+                        face.spawn_bundle(image(
+                            &asset_server,
+                            &mut materials,
+                            path,
+                            *size,
+                            *position,
+                        ))
+                        .insert(ClockHand::Gear(*speed));
+                    }
+                    face.spawn_bundle(image(
+                        &asset_server,
+                        &mut materials,
+                        "ui/clock/gearcover.png",
+                        FACE_SIZE,
+                        centre,
+                    ));
+                    for (path, hand) in [
+                        ("ui/clock/hourhand.png", ClockHand::Hour),
+                        ("ui/clock/minutehand.png", ClockHand::Minute),
+                        ("ui/clock/secondhand.png", ClockHand::Second),
+                    ]
+                    .iter()
+                    {
+                        face.spawn_bundle(image(
+                            &asset_server,
+                            &mut materials,
+                            path,
+                            FACE_SIZE,
+                            centre,
+                        ))
+                        .insert(*hand);
+                    }
+                });
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.0)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font:      asset_server
+                                .load("fonts/OpenSans-Semibold.ttf"),
+                            font_size: 18.0,
+                            color:     Color::WHITE,
+                        },
+                        TextAlignment {
+                            horizontal: HorizontalAlign::Center,
+                            ..Default::default()
+                        },
+                    ),
+                    ..Default::default()
+                })
+                .insert(ClockReadout);
+        });
+}
+
+pub fn update_clock(
+    game_time: Res<GameTime>,
+    rate: Res<GameTimeRate>,
+    mut hands: Query<(&ClockHand, &mut Transform)>,
+    mut readouts: Query<&mut Text, With<ClockReadout>>,
+) {
+    if !game_time.is_changed() && !rate.is_changed() {
+        return;
+    }
+    for (hand, mut transform) in hands.iter_mut() {
+        transform.rotation =
+            Quat::from_rotation_z(-hand.angle(*game_time).to_radians());
+    }
+    for mut text in readouts.iter_mut() {
+        text.sections[0].value = readout(*game_time, &rate);
+    }
+}
+
+pub fn time_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut rate: ResMut<GameTimeRate>,
+) {
+    if keyboard.just_pressed(KeyCode::Space) {
+        rate.toggle_pause();
+    }
+    if keyboard.just_pressed(KeyCode::Equals)
+        || keyboard.just_pressed(KeyCode::NumpadAdd)
+    {
+        let faster = (rate.rate() * 2.0).min(MAX_RATE);
+        rate.set_rate(faster);
+    }
+    if keyboard.just_pressed(KeyCode::Minus)
+        || keyboard.just_pressed(KeyCode::NumpadSubtract)
+    {
+        let slower = (rate.rate() / 2.0).max(MIN_RATE);
+        rate.set_rate(slower);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::time::Stamp;
+
+    #[test]
+    fn hands_and_readout_follow_time() {
+        let time = GameTime::from_stamp(&Stamp {
+            day:    2,
+            hour:   15,
+            minute: 30,
+            second: 10,
+        });
+        assert!((ClockHand::Hour.angle(time) - 105.0).abs() < 0.1);
+        assert!((ClockHand::Minute.angle(time) - 181.0).abs() < 0.1);
+        assert!((ClockHand::Second.angle(time) - 60.0).abs() < 0.1);
+        let mut rate = GameTimeRate::new(1.5);
+        assert_eq!(readout(time, &rate), "Day 2 03:30:10 PM\nx1.5");
+        rate.toggle_pause();
+        assert!(readout(time, &rate).ends_with("Paused"));
+    }
+}
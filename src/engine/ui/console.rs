@@ -0,0 +1,205 @@
+// Command console:
+// A text-adventure prompt for the player along the top of the screen. The
+// backquote key opens and closes it (as does Escape), and while it is open
+// the keyboard belongs to it. Enter hands the line to actor::command, whose
+// Tasks the player carries out like any other actor; replies and complaints
+// are printed below what was typed.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+
+use crate::engine::actor::command::{parse, visible_to, Command};
+use crate::engine::actor::player::Player;
+use crate::engine::actor::{Path, Task};
+use crate::engine::world::{Destination, Position, TileEntityMap, TileWeightMap};
+use crate::engine::Identity;
+
+const HISTORY: usize = 50; // Lines kept
+const VISIBLE_LINES: usize = 6;
+
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    input:    String,
+    pending:  Vec<String>, // Entered, yet to be run
+    history:  VecDeque<String>,
+}
+impl Console {
+    pub fn print(
+        &mut self,
+        line: String,
+    ) {
+        if self.history.len() == HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(line);
+    }
+}
+
+pub struct ConsoleNode;
+pub struct ConsoleText;
+
+pub fn spawn_console(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load("fonts/OpenSans-Regular.ttf");
+    // Between the side panel and the clock
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                size: Size::new(Val::Auto, Val::Auto),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(250.0),
+                    right: Val::Px(180.0),
+                    top: Val::Px(10.0),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.05, 0.05, 0.08, 0.9).into()),
+            visible: Visible {
+                is_visible:     false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(Interaction::default()) // Keeps clicks from picking behind
+        .insert(ConsoleNode)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        String::new(),
+                        TextStyle {
+                            font,
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible:     false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(ConsoleText);
+        });
+}
+
+pub fn console_input(
+    mut keyboard: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut console: ResMut<Console>,
+) {
+    // Runs straight after bevy reads the keyboard, so that the keys typed
+    // here are gone before the bindings look at them
+    if keyboard.just_pressed(KeyCode::Grave) {
+        console.open = !console.open;
+    } else if keyboard.just_pressed(KeyCode::Escape) {
+        console.open = false;
+    }
+    // Read even while closed, so nothing typed earlier turns up later
+    let typed: String = characters
+        .iter()
+        .map(|character| character.char)
+        .filter(|character| !character.is_control() && *character != '`')
+        .collect();
+    if !console.open {
+        return;
+    }
+    console.input.push_str(&typed);
+    if keyboard.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keyboard.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input);
+        console.pending.push(line);
+    }
+    let pressed: Vec<KeyCode> = keyboard.get_pressed().copied().collect();
+    for key in pressed {
+        keyboard.reset(key);
+    }
+}
+
+pub fn run_console_commands(
+    mut commands: Commands,
+    mut console: ResMut<Console>,
+    entity_map: Res<TileEntityMap>,
+    weight_map: Res<TileWeightMap>,
+    identities: Query<&Identity>,
+    players: Query<(Entity, &Position), With<Player>>,
+) {
+    if console.pending.is_empty() {
+        return;
+    }
+    for line in std::mem::take(&mut console.pending) {
+        console.print(format!("> {}", line));
+        let (player, here) = match players.iter().next() {
+            Some(player) => player,
+            None => {
+                console.print("There is no one to command.".to_owned());
+                continue;
+            }
+        };
+        let seen = visible_to(player, here, &entity_map, &identities);
+        let walkable = |position: &Position| {
+            weight_map.get(position.x, position.y) < i64::MAX
+        };
+        let occupied =
+            |position: &Position| entity_map.is_blocked(position.x, position.y);
+        match parse(&line, *here, &seen, walkable, occupied) {
+            Ok(Command::Act(task)) => {
+                // Replaces whatever the player was doing
+                commands
+                    .entity(player)
+                    .remove::<Task>()
+                    .remove::<Destination>()
+                    .remove::<Path>()
+                    .insert(task);
+            }
+            Ok(Command::Reply(text)) => console.print(text),
+            Err(error) => console.print(error.to_string()),
+        }
+    }
+}
+
+pub fn update_console(
+    console: Res<Console>,
+    mut nodes: Query<(&mut Style, &mut Visible), With<ConsoleNode>>,
+    mut texts: Query<
+        (&mut Text, &mut Visible),
+        (With<ConsoleText>, Without<ConsoleNode>),
+    >,
+) {
+    if !console.is_changed() {
+        return;
+    }
+    let display = if console.open {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    for (mut style, mut visible) in nodes.iter_mut() {
+        style.display = display;
+        visible.is_visible = console.open;
+    }
+    let skip = console.history.len().saturating_sub(VISIBLE_LINES);
+    let mut value = String::new();
+    for line in console.history.iter().skip(skip) {
+        value.push_str(line);
+        value.push('\n');
+    }
+    value.push_str(&format!("> {}_", console.input));
+    for (mut text, mut visible) in texts.iter_mut() {
+        text.sections[0].value = value.clone();
+        visible.is_visible = console.open;
+    }
+}
@@ -0,0 +1,365 @@
+// Inspector:
+// Lists every component of the Selected entity together with its value, in a
+// window toggled by F3. F1 prints the same listing to stdout, for every entity
+// when nothing is selected. While the window is open, right clicking a tile
+// teleports the selected actor there, shift + right click makes it the
+// actor's destination, and PageUp / PageDown change its hunger. Changes are
+// sent as InspectorEdit events so other tools can make them too.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::{ecs::component::Component, prelude::*};
+
+use crate::engine::actor::{LastStep, Orientation, Path, Routine, Status, Task};
+use crate::engine::render::{cursor_to_world, MainCamera, Projection};
+use crate::engine::world::{spatial::SpatialIndex, time::GameTime, Destination,
+                           Occupancy, Position, TileEntityMap, TileWeightMap};
+use crate::engine::{Identity, Selected};
+
+const HUNGER_STEP: u32 = 10;
+
+#[derive(Default)]
+pub struct Inspector {
+    pub visible: bool,
+    shown:       bool, // Whether the window was drawn last frame
+}
+
+pub struct InspectorWindow;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InspectorEdit {
+    Teleport(Entity, Position),
+    SetDestination(Entity, Position),
+    SetHunger(Entity, u32),
+}
+
+type Formatter = fn(&World, Entity) -> Option<String>;
+
+fn debug_value<T: Component + fmt::Debug>(
+    world: &World,
+    entity: Entity,
+) -> Option<String> {
+    world
+        .get::<T>(entity)
+        .map(|component| format!("{:?}", component))
+}
+
+fn formatters() -> HashMap<TypeId, Formatter> {
+    // Components without an entry are listed by name only
+    let mut formatters: HashMap<TypeId, Formatter> = HashMap::new();
+    formatters.insert(TypeId::of::<Identity>(), debug_value::<Identity>);
+    formatters.insert(TypeId::of::<Position>(), debug_value::<Position>);
+    formatters.insert(TypeId::of::<Destination>(), debug_value::<Destination>);
+    formatters.insert(TypeId::of::<Occupancy>(), debug_value::<Occupancy>);
+    formatters.insert(TypeId::of::<GameTime>(), debug_value::<GameTime>);
+    formatters.insert(TypeId::of::<Orientation>(), debug_value::<Orientation>);
+    formatters.insert(TypeId::of::<Path>(), debug_value::<Path>);
+    formatters.insert(TypeId::of::<Task>(), debug_value::<Task>);
+    formatters.insert(TypeId::of::<Status>(), debug_value::<Status>);
+    formatters.insert(TypeId::of::<Routine>(), debug_value::<Routine>);
+    formatters
+}
+
+pub fn short_name(name: &str) -> String {
+    // Drops module paths, also inside generics:
+    // "bevy_asset::handle::Handle<bevy_sprite::TextureAtlas>" becomes
+    // "Handle<TextureAtlas>"
+    let mut short = String::new();
+    let mut segment = String::new();
+    for character in name.chars() {
+        if character.is_alphanumeric() || character == '_' || character == ':' {
+            segment.push(character);
+        } else {
+            short.push_str(segment.rsplit("::").next().unwrap_or(""));
+            segment.clear();
+            short.push(character);
+        }
+    }
+    short.push_str(segment.rsplit("::").next().unwrap_or(""));
+    short
+}
+
+pub fn describe_entity(
+    world: &World,
+    entity: Entity,
+) -> Vec<(String, Option<String>)> {
+    // (component name, value) pairs, sorted by name
+    let formatters = formatters();
+    let archetype = match world
+        .entities()
+        .get(entity)
+        .and_then(|location| world.archetypes().get(location.archetype_id))
+    {
+        Some(archetype) => archetype,
+        None => return Vec::new(),
+    };
+    let mut fields: Vec<_> = archetype
+        .components()
+        .filter_map(|id| world.components().get_info(id))
+        .map(|info| {
+            let value = info
+                .type_id()
+                .and_then(|type_id| formatters.get(&type_id))
+                .and_then(|format| format(world, entity));
+            (short_name(info.name()), value)
+        })
+        .collect();
+    fields.sort();
+    fields
+}
+
+pub fn format_entity(
+    entity: Entity,
+    fields: &[(String, Option<String>)],
+) -> String {
+    let mut text = format!("Entity {:?}", entity);
+    for (name, value) in fields {
+        match value {
+            Some(value) => text.push_str(&format!("\n\t{}: {}", name, value)),
+            None => text.push_str(&format!("\n\t{}", name)),
+        }
+    }
+    text
+}
+
+fn selected_entity(world: &mut World) -> Option<Entity> {
+    let mut query = world.query_filtered::<Entity, With<Selected>>();
+    let selected = query.iter(world).next();
+    selected
+}
+
+pub fn dump_entities(world: &mut World) {
+    // Headless counterpart of the window, also usable without a UI
+    let pressed = world
+        .get_resource::<Input<KeyCode>>()
+        .map_or(false, |keyboard| keyboard.just_pressed(KeyCode::F1));
+    if !pressed {
+        return;
+    }
+    let entities: Vec<Entity> = match selected_entity(world) {
+        Some(entity) => vec![entity],
+        None => world.query::<Entity>().iter(world).collect(),
+    };
+    for entity in entities {
+        println!("{}", format_entity(entity, &describe_entity(world, entity)));
+    }
+}
+
+pub fn spawn_inspector(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let hidden = Visible {
+        is_visible:     false,
+        is_transparent: true,
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(320.0), Val::Auto),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    top: Val::Px(230.0),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.1, 0.1, 0.12, 0.8).into()),
+            visible: hidden.clone(),
+            ..Default::default()
+        })
+        .insert(InspectorWindow)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "",
+                        TextStyle {
+                            font:      asset_server
+                                .load("fonts/OpenSans-Regular.ttf"),
+                            font_size: 14.0,
+                            color:     Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: hidden,
+                    ..Default::default()
+                })
+                .insert(InspectorWindow);
+        });
+}
+
+pub fn update_inspector(world: &mut World) {
+    let visible = match world.get_resource::<Inspector>() {
+        Some(inspector) if inspector.visible || inspector.shown => {
+            inspector.visible
+        }
+        _ => return,
+    };
+    let contents = if visible {
+        match selected_entity(world) {
+            Some(entity) => {
+                format_entity(entity, &describe_entity(world, entity))
+            }
+            None => "Nothing selected".to_owned(),
+        }
+    } else {
+        String::new()
+    };
+    let mut windows = world.query_filtered::<(
+        &mut Visible,
+        Option<&mut Text>,
+    ), With<InspectorWindow>>();
+    for (mut window_visible, text) in windows.iter_mut(world) {
+        window_visible.is_visible = visible;
+        if let Some(mut text) = text {
+            text.sections[0].value = contents.clone();
+        }
+    }
+    if let Some(mut inspector) = world.get_resource_mut::<Inspector>() {
+        inspector.shown = visible;
+    }
+}
+
+pub fn inspector_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    projection: Res<Projection>,
+    mut inspector: ResMut<Inspector>,
+    mut edits: EventWriter<InspectorEdit>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    selected: Query<(Entity, Option<&Status>), With<Selected>>,
+) {
+    if keyboard.just_pressed(KeyCode::F3) {
+        inspector.visible = !inspector.visible;
+    }
+    let (entity, status) = match selected.iter().next() {
+        Some(selected) if inspector.visible => selected,
+        _ => return,
+    };
+    if mouse.just_pressed(MouseButton::Right) {
+        let tile = match (windows.get_primary(), cameras.iter().next()) {
+            (Some(window), Some(camera_transform)) => {
+                cursor_to_world(window, camera_transform)
+                    .map(|world_position| projection.to_tile(world_position))
+            }
+            _ => None,
+        };
+        let shift = keyboard.pressed(KeyCode::LShift)
+            || keyboard.pressed(KeyCode::RShift);
+        match tile {
+            Some(tile) if shift => {
+                edits.send(InspectorEdit::SetDestination(entity, tile))
+            }
+            Some(tile) => edits.send(InspectorEdit::Teleport(entity, tile)),
+            None => (),
+        }
+    }
+    if let Some(status) = status {
+        if keyboard.just_pressed(KeyCode::PageUp) {
+            let hunger = status.hunger().saturating_add(HUNGER_STEP);
+            edits.send(InspectorEdit::SetHunger(entity, hunger));
+        }
+        if keyboard.just_pressed(KeyCode::PageDown) {
+            let hunger = status.hunger().saturating_sub(HUNGER_STEP);
+            edits.send(InspectorEdit::SetHunger(entity, hunger));
+        }
+    }
+}
+
+pub fn apply_inspector_edits(
+    mut commands: Commands,
+    mut edits: EventReader<InspectorEdit>,
+    mut entity_map: ResMut<TileEntityMap>,
+    mut spatial_index: ResMut<SpatialIndex>,
+    weight_map: Res<TileWeightMap>,
+    mut positions: Query<(
+        &mut Position,
+        Option<&Occupancy>,
+        Option<&mut LastStep>,
+    )>,
+    mut statuses: Query<&mut Status>,
+) {
+    for edit in edits.iter() {
+        match *edit {
+            InspectorEdit::Teleport(entity, to) => {
+                let (mut position, occupancy, last_step) =
+                    match positions.get_mut(entity) {
+                        Ok(found) => found,
+                        Err(_) => {
+                            warn!(
+                                "Cannot teleport {:?}, it has no position",
+                                entity
+                            );
+                            continue;
+                        }
+                    };
+                // Nothing may stand inside walls or off the map
+                if weight_map.get(to.x, to.y) == i64::MAX {
+                    warn!(
+                        "Cannot teleport {:?} to {:?}, it is not walkable",
+                        entity, to
+                    );
+                    continue;
+                }
+                // Entities without Occupancy are not tracked on the map
+                if let Some(occupancy) = occupancy {
+                    if let Err(error) = entity_map.place(entity, to, *occupancy)
+                    {
+                        warn!("Cannot teleport {:?}: {:?}", entity, error);
+                        continue;
+                    }
+                }
+                *position = to;
+                spatial_index.insert(entity, to);
+                if let Some(mut last_step) = last_step {
+                    last_step.from = to; // Jump rather than slide
+                }
+                // The remaining path started elsewhere
+                commands.entity(entity).remove::<Path>();
+            }
+            InspectorEdit::SetDestination(entity, to) => {
+                commands
+                    .entity(entity)
+                    .insert(Destination(to))
+                    .remove::<Path>();
+            }
+            InspectorEdit::SetHunger(entity, hunger) => {
+                match statuses.get_mut(entity) {
+                    Ok(mut status) => status.set_hunger(hunger),
+                    Err(_) => warn!("{:?} has no Status to edit", entity),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_names_drop_module_paths() {
+        assert_eq!(
+            short_name("groundhog::engine::world::Position"),
+            "Position"
+        );
+        assert_eq!(
+            short_name("bevy_asset::handle::Handle<bevy_sprite::TextureAtlas>"),
+            "Handle<TextureAtlas>"
+        );
+        assert_eq!(
+            format_entity(Entity::new(3), &[
+                ("Position".to_owned(), Some("(1, 2)".to_owned())),
+                ("Selected".to_owned(), None),
+            ]),
+            "Entity 3v0\n\tPosition: (1, 2)\n\tSelected"
+        );
+    }
+}
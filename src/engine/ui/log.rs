@@ -0,0 +1,216 @@
+// Narrative log panel:
+// The NarrativeLog told as English sentences in a strip along the bottom of
+// the screen, newest at the bottom. F8 shows or hides it, F9 cycles between
+// everyone, the Selected actor and the area around the selected tile, and
+// F10 writes the filtered log to narrative.txt. The wheel scrolls it while
+// the cursor is over it.
+
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+
+use crate::engine::actor::narrative::{LogFilter, NarrativeLog, Narrator};
+use crate::engine::actor::player::Player;
+use crate::engine::render::picking::SelectedTile;
+use crate::engine::{Identity, Selected};
+
+const EXPORT_PATH: &str = "narrative.txt";
+const PANEL_HEIGHT: f32 = 180.0;
+const VISIBLE_LINES: usize = 8;
+const ZONE_RADIUS: i64 = 10; // Tiles around the selected tile
+
+#[derive(Default)]
+pub struct LogPanel {
+    pub visible: bool,
+    pub filter:  LogFilter,
+    scroll:      usize, // Lines up from the newest
+}
+
+pub struct LogPanelNode;
+pub struct LogText;
+
+pub fn transcript(
+    log: &NarrativeLog,
+    filter: &LogFilter,
+    observer: Option<Entity>,
+    name_of: impl Fn(Entity) -> Option<String>,
+) -> Vec<String> {
+    // The observer, normally the player, speaks in the first person
+    let mut narrator = Narrator::new(observer);
+    log.filtered(filter)
+        .map(|entry| {
+            format!("[{}] {}", entry.time, narrator.tell(entry, &name_of))
+        })
+        .collect()
+}
+
+fn filter_label(
+    filter: &LogFilter,
+    name_of: impl Fn(Entity) -> Option<String>,
+) -> String {
+    match *filter {
+        LogFilter::All => "everyone".to_owned(),
+        LogFilter::Actor(entity) => {
+            name_of(entity).unwrap_or_else(|| format!("entity {}", entity.id()))
+        }
+        LogFilter::Zone(centre, radius) => {
+            format!("within {} of ({}, {})", radius, centre.x, centre.y)
+        }
+    }
+}
+
+pub fn spawn_log_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let font = asset_server.load("fonts/OpenSans-Regular.ttf");
+    // Between the side panel and the minimap
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                size: Size::new(Val::Auto, Val::Px(PANEL_HEIGHT)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(250.0),
+                    right: Val::Px(220.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                padding: Rect::all(Val::Px(8.0)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.1, 0.1, 0.12, 0.8).into()),
+            visible: Visible {
+                is_visible:     false,
+                is_transparent: true,
+            },
+            ..Default::default()
+        })
+        .insert(Interaction::default()) // Keeps clicks from picking behind
+        .insert(LogPanelNode)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        String::new(),
+                        TextStyle {
+                            font,
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    visible: Visible {
+                        is_visible:     false,
+                        is_transparent: true,
+                    },
+                    ..Default::default()
+                })
+                .insert(LogText);
+        });
+}
+
+pub fn log_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut scroll_events: EventReader<MouseWheel>,
+    log: Res<NarrativeLog>,
+    selected_tile: Res<SelectedTile>,
+    mut panel: ResMut<LogPanel>,
+    selected: Query<Entity, With<Selected>>,
+    identities: Query<&Identity>,
+    players: Query<Entity, With<Player>>,
+    nodes: Query<&Interaction, With<LogPanelNode>>,
+) {
+    if keyboard.just_pressed(KeyCode::F8) {
+        panel.visible = !panel.visible;
+    }
+    if keyboard.just_pressed(KeyCode::F9) {
+        // Skips the filters that have nothing to filter by
+        let actor = selected.iter().next().map(LogFilter::Actor);
+        let zone = selected_tile
+            .0
+            .map(|tile| LogFilter::Zone(tile, ZONE_RADIUS));
+        panel.filter = match panel.filter {
+            LogFilter::All => actor.or(zone),
+            LogFilter::Actor(_) => zone,
+            LogFilter::Zone(..) => None,
+        }
+        .unwrap_or(LogFilter::All);
+        panel.scroll = 0;
+    }
+    let name_of = |entity| {
+        identities
+            .get(entity)
+            .ok()
+            .filter(|identity| identity.specific)
+            .map(|identity| identity.name.clone())
+    };
+    if keyboard.just_pressed(KeyCode::F10) {
+        let mut text =
+            transcript(&log, &panel.filter, players.iter().next(), name_of)
+                .join("\n");
+        text.push('\n');
+        match std::fs::write(EXPORT_PATH, text) {
+            Ok(()) => info!("Exported {}", EXPORT_PATH),
+            Err(error) => warn!("Could not export {}: {}", EXPORT_PATH, error),
+        }
+    }
+    let hovered = panel.visible
+        && nodes
+            .iter()
+            .any(|interaction| *interaction != Interaction::None);
+    for event in scroll_events.iter() {
+        if hovered {
+            let lines = event.y.round() as i64;
+            let scroll = (panel.scroll as i64 + lines).max(0) as usize;
+            panel.scroll = scroll.min(log.len().saturating_sub(VISIBLE_LINES));
+        }
+    }
+}
+
+pub fn update_log_panel(
+    log: Res<NarrativeLog>,
+    panel: Res<LogPanel>,
+    identities: Query<&Identity>,
+    players: Query<Entity, With<Player>>,
+    mut nodes: Query<(&mut Style, &mut Visible), With<LogPanelNode>>,
+    mut texts: Query<
+        (&mut Text, &mut Visible),
+        (With<LogText>, Without<LogPanelNode>),
+    >,
+) {
+    let display = if panel.visible {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    for (mut style, mut visible) in nodes.iter_mut() {
+        // Only touched on change, as it lays the UI out again
+        if style.display != display {
+            style.display = display;
+        }
+        visible.is_visible = panel.visible;
+    }
+    for (_, mut visible) in texts.iter_mut() {
+        visible.is_visible = panel.visible;
+    }
+    if !panel.visible || !(log.is_changed() || panel.is_changed()) {
+        return;
+    }
+    let name_of = |entity| {
+        identities
+            .get(entity)
+            .ok()
+            .filter(|identity| identity.specific)
+            .map(|identity| identity.name.clone())
+    };
+    let lines = transcript(&log, &panel.filter, players.iter().next(), name_of);
+    let end = lines.len().saturating_sub(panel.scroll);
+    let start = end.saturating_sub(VISIBLE_LINES);
+    let mut value = format!("Log: {}\n", filter_label(&panel.filter, name_of));
+    value.push_str(&lines[start..end].join("\n"));
+    for (mut text, _) in texts.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
@@ -0,0 +1,395 @@
+// Minimap:
+// The whole map in the bottom right corner, one pixel per tile or per block
+// of tiles on large maps, drawn top row (highest y) first. The ground is
+// coloured by its type and redrawn when the weights change; actors are dots,
+// white when Selected, cyan while walking, red when their last plan failed
+// and yellow otherwise, and a frame marks what the main camera sees. Clicking
+// it recentres the camera there, M hides it.
+
+use bevy::{prelude::*,
+           render::texture::{Extent3d, FilterMode, TextureDimension,
+                             TextureFormat}};
+
+use crate::engine::actor::{Path, PathFailed};
+use crate::engine::render::camera_movement::CameraControl;
+use crate::engine::render::{MainCamera, Projection};
+use crate::engine::world::{GroundType, Position, TileWeightMap};
+use crate::engine::Selected;
+
+const MINIMAP_SIZE: f32 = 200.0; // Screen pixels
+const MAX_PIXELS: i64 = 128; // Per side of the texture
+const UNKNOWN_GROUND: [u8; 4] = [90, 90, 90, 255];
+const GROUND_COLORS: [(GroundType, [u8; 4]); 7] = [
+    (GroundType::ShortGrass, [84, 140, 60, 255]),
+    (GroundType::TallGrass, [50, 100, 40, 255]),
+    (GroundType::Sidewalk, [170, 170, 165, 255]),
+    (GroundType::Path, [160, 130, 90, 255]),
+    (GroundType::Street, [60, 60, 66, 255]),
+    (GroundType::Crosswalk, [220, 220, 210, 255]),
+    (GroundType::Obstacle, [20, 20, 24, 255]),
+];
+const SELECTED_DOT: [u8; 4] = [255, 255, 255, 255];
+const WALKING_DOT: [u8; 4] = [60, 220, 255, 255];
+const FAILED_DOT: [u8; 4] = [255, 50, 50, 255];
+const IDLE_DOT: [u8; 4] = [255, 220, 60, 255];
+const VIEWPORT_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+pub struct MinimapImage;
+
+// Pixels of a map scaled down by `stride` tiles per pixel
+#[derive(Clone)]
+pub struct MinimapRaster {
+    pub stride:  i64,
+    pub columns: u32,
+    pub rows:    u32,
+    pub data:    Vec<u8>, // RGBA, top row first
+}
+impl MinimapRaster {
+    pub fn new(
+        width: i64,
+        height: i64,
+    ) -> Self {
+        let stride = ((width.max(height) + MAX_PIXELS - 1) / MAX_PIXELS).max(1);
+        let columns = ((width + stride - 1) / stride) as u32;
+        let rows = ((height + stride - 1) / stride) as u32;
+        Self {
+            stride,
+            columns,
+            rows,
+            data: vec![0; (columns * rows * 4) as usize],
+        }
+    }
+    pub fn pixel_of(
+        &self,
+        position: &Position,
+    ) -> Option<(u32, u32)> {
+        // (column, row) of a tile
+        if position.x < 0 || position.y < 0 {
+            return None;
+        }
+        let column = (position.x / self.stride) as u32;
+        let row_from_bottom = (position.y / self.stride) as u32;
+        if column >= self.columns || row_from_bottom >= self.rows {
+            return None;
+        }
+        Some((column, self.rows - 1 - row_from_bottom))
+    }
+    pub fn put(
+        &mut self,
+        column: u32,
+        row: u32,
+        color: [u8; 4],
+    ) {
+        if column < self.columns && row < self.rows {
+            let start = ((row * self.columns + column) * 4) as usize;
+            self.data[start..start + 4].copy_from_slice(&color);
+        }
+    }
+    pub fn tile_at(
+        &self,
+        fraction: Vec2,
+    ) -> Position {
+        // The tile under a point given as a fraction of the image from its
+        // bottom left corner; the image spans whole blocks, past the map
+        let extent = |pixels: u32| (i64::from(pixels) * self.stride) as f32;
+        Position {
+            x: (fraction.x * extent(self.columns)).floor() as i64,
+            y: (fraction.y * extent(self.rows)).floor() as i64,
+        }
+    }
+    pub fn paint_ground(
+        &mut self,
+        weight_map: &TileWeightMap,
+    ) {
+        // Each pixel takes the ground of the first tile in its block
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                let x = i64::from(column) * self.stride;
+                let y = i64::from(self.rows - 1 - row) * self.stride;
+                let weight = weight_map.get(x, y);
+                let color = GROUND_COLORS
+                    .iter()
+                    .find(|(ground, _)| ground.weight() == weight)
+                    .map_or(UNKNOWN_GROUND, |(_, color)| *color);
+                self.put(column, row, color);
+            }
+        }
+    }
+    pub fn plot(
+        &mut self,
+        position: &Position,
+        color: [u8; 4],
+    ) {
+        if let Some((column, row)) = self.pixel_of(position) {
+            self.put(column, row, color);
+        }
+    }
+    pub fn frame(
+        &mut self,
+        min: &Position,
+        max: &Position,
+        color: [u8; 4],
+    ) {
+        // Outline of the tiles between two corners, clipped to the map
+        let clip = |position: &Position| {
+            let x = position
+                .x
+                .clamp(0, i64::from(self.columns) * self.stride - 1);
+            let y = position.y.clamp(0, i64::from(self.rows) * self.stride - 1);
+            self.pixel_of(&Position { x, y })
+        };
+        let (left, bottom) = match clip(min) {
+            Some(pixel) => pixel,
+            None => return,
+        };
+        let (right, top) = match clip(max) {
+            Some(pixel) => pixel,
+            None => return,
+        };
+        for column in left..=right {
+            self.put(column, top, color);
+            self.put(column, bottom, color);
+        }
+        for row in top..=bottom {
+            self.put(left, row, color);
+            self.put(right, row, color);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Minimap {
+    pub visible: bool,
+    texture:     Option<Handle<Texture>>,
+    ground:      Option<MinimapRaster>, // Redrawn when the weights change
+}
+
+pub fn spawn_minimap(
+    mut commands: Commands,
+    weight_map: Res<TileWeightMap>,
+    mut minimap: ResMut<Minimap>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let raster = MinimapRaster::new(weight_map.width(), weight_map.height());
+    let mut texture = Texture::new(
+        Extent3d::new(raster.columns, raster.rows, 1),
+        TextureDimension::D2,
+        raster.data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    texture.sampler.mag_filter = FilterMode::Nearest;
+    texture.sampler.min_filter = FilterMode::Nearest;
+    let handle = textures.add(texture);
+    // Keeps the map's aspect ratio within a MINIMAP_SIZE square
+    let aspect = raster.columns as f32 / raster.rows as f32;
+    let size = if aspect >= 1.0 {
+        Size::new(Val::Px(MINIMAP_SIZE), Val::Px(MINIMAP_SIZE / aspect))
+    } else {
+        Size::new(Val::Px(MINIMAP_SIZE * aspect), Val::Px(MINIMAP_SIZE))
+    };
+    commands
+        .spawn_bundle(ImageBundle {
+            style: Style {
+                size,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: materials.add(ColorMaterial::texture(handle.clone())),
+            ..Default::default()
+        })
+        .insert(Interaction::default())
+        .insert(MinimapImage);
+    minimap.visible = true;
+    minimap.texture = Some(handle);
+}
+
+pub fn update_minimap(
+    keyboard: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    projection: Res<Projection>,
+    weight_map: Res<TileWeightMap>,
+    mut minimap: ResMut<Minimap>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut images: Query<(&mut Style, &mut Visible), With<MinimapImage>>,
+    cameras: Query<&Transform, With<MainCamera>>,
+    actors: Query<(
+        &Position,
+        Option<&Selected>,
+        Option<&Path>,
+        Option<&PathFailed>,
+    )>,
+) {
+    if keyboard.just_pressed(KeyCode::M) {
+        minimap.visible = !minimap.visible;
+    }
+    // Hidden from layout as well, so it stops catching the mouse
+    let display = if minimap.visible {
+        Display::Flex
+    } else {
+        Display::None
+    };
+    for (mut style, mut visible) in images.iter_mut() {
+        if style.display != display {
+            style.display = display;
+        }
+        visible.is_visible = minimap.visible;
+    }
+    if !minimap.visible {
+        return;
+    }
+    if weight_map.is_changed() || minimap.ground.is_none() {
+        let mut ground =
+            MinimapRaster::new(weight_map.width(), weight_map.height());
+        ground.paint_ground(&weight_map);
+        minimap.ground = Some(ground);
+    }
+    let mut raster = match &minimap.ground {
+        Some(ground) => ground.clone(),
+        None => return,
+    };
+    for (position, selected, path, failed) in actors.iter() {
+        let color = match (selected, failed, path) {
+            (Some(_), _, _) => SELECTED_DOT,
+            (None, Some(_), _) => FAILED_DOT,
+            (None, None, Some(_)) => WALKING_DOT,
+            (None, None, None) => IDLE_DOT,
+        };
+        raster.plot(position, color);
+    }
+    if let (Some(window), Some(camera)) =
+        (windows.get_primary(), cameras.iter().next())
+    {
+        let half = Vec2::new(window.width(), window.height()) / 2.0
+            * camera.scale.truncate();
+        let centre = camera.translation.truncate();
+        let corners: Vec<Position> = [
+            Vec2::new(-half.x, -half.y),
+            Vec2::new(half.x, -half.y),
+            Vec2::new(-half.x, half.y),
+            Vec2::new(half.x, half.y),
+        ]
+        .iter()
+        .map(|corner| projection.to_tile(centre + *corner))
+        .collect();
+        let min = Position {
+            x: corners.iter().map(|corner| corner.x).min().unwrap_or(0),
+            y: corners.iter().map(|corner| corner.y).min().unwrap_or(0),
+        };
+        let max = Position {
+            x: corners.iter().map(|corner| corner.x).max().unwrap_or(0),
+            y: corners.iter().map(|corner| corner.y).max().unwrap_or(0),
+        };
+        raster.frame(&min, &max, VIEWPORT_COLOR);
+    }
+    if let Some(texture) = minimap
+        .texture
+        .as_ref()
+        .and_then(|handle| textures.get_mut(handle))
+    {
+        texture.data = raster.data;
+    }
+}
+
+pub fn minimap_click(
+    mouse: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    projection: Res<Projection>,
+    minimap: Res<Minimap>,
+    mut control: ResMut<CameraControl>,
+    images: Query<(&Interaction, &Node, &GlobalTransform), With<MinimapImage>>,
+    mut cameras: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !minimap.visible || !mouse.pressed(MouseButton::Left) {
+        return;
+    }
+    let raster = match &minimap.ground {
+        Some(raster) => raster,
+        None => return,
+    };
+    let cursor = match windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+    {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    for (interaction, node, transform) in images.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        // UI coordinates share the window's bottom left origin
+        let corner = transform.translation.truncate() - node.size / 2.0;
+        let fraction = (cursor - corner) / node.size;
+        let target = projection.to_world(&raster.tile_at(fraction));
+        control.follow = None;
+        for mut camera in cameras.iter_mut() {
+            camera.translation.x = target.x;
+            camera.translation.y = target.y;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn large_maps_share_pixels() {
+        let mut raster = MinimapRaster::new(300, 150);
+        assert_eq!((raster.stride, raster.columns, raster.rows), (3, 100, 50));
+        assert_eq!(raster.pixel_of(&Position { x: 5, y: 0 }), Some((1, 49)));
+        assert_eq!(
+            raster.pixel_of(&Position { x: 299, y: 149 }),
+            Some((99, 0))
+        );
+        assert_eq!(raster.pixel_of(&Position { x: 300, y: 0 }), None);
+        raster.plot(&Position { x: 0, y: 149 }, IDLE_DOT);
+        assert_eq!(&raster.data[..4], &IDLE_DOT);
+        raster.frame(
+            &Position { x: -10, y: -10 },
+            &Position { x: 5, y: 5 },
+            VIEWPORT_COLOR,
+        );
+        let bottom_right = ((49 * 100 + 1) * 4) as usize;
+        assert_eq!(
+            &raster.data[bottom_right..bottom_right + 4],
+            &VIEWPORT_COLOR
+        );
+        // 300 tiles over 100 pixels of 3, 150 over 50
+        assert_eq!(raster.tile_at(Vec2::new(0.999, 0.5)), Position {
+            x: 299,
+            y: 75,
+        });
+    }
+
+    #[test]
+    fn ground_is_coloured_by_type() {
+        let mut weight_map = TileWeightMap::new(3, 2);
+        weight_map.set(0, 1, GroundType::Street.weight());
+        weight_map.set(1, 1, GroundType::Sidewalk.weight());
+        weight_map.set(2, 1, GroundType::Obstacle.weight());
+        weight_map.set(0, 0, GroundType::ShortGrass.weight());
+        let mut raster = MinimapRaster::new(3, 2);
+        raster.paint_ground(&weight_map);
+        let color = |ground| {
+            GROUND_COLORS
+                .iter()
+                .find(|(other, _)| *other == ground)
+                .map(|(_, color)| *color)
+                .unwrap()
+        };
+        let pixel = |index: usize| &raster.data[index * 4..index * 4 + 4];
+        // The top row, y = 1, comes first
+        assert_eq!(pixel(0), &color(GroundType::Street));
+        assert_eq!(pixel(1), &color(GroundType::Sidewalk));
+        assert_eq!(pixel(2), &color(GroundType::Obstacle));
+        assert_eq!(pixel(3), &color(GroundType::ShortGrass));
+        assert_eq!(pixel(4), &UNKNOWN_GROUND);
+    }
+}
@@ -1,259 +1,53 @@
+// This module builds the on-screen interface drawn over the world: widgets
+// live in submodules and are spawned as bevy UI nodes, drawn by the UI camera
+// of render. The clock brings its own ClockPlugin.
+
+use bevy::input::InputSystem;
 use bevy::prelude::*;
 
-pub struct UIPlugin();
+pub mod clock;
+mod console;
+pub mod inspector;
+mod log;
+mod minimap;
+mod panel;
+
+pub struct UIPlugin;
 
 impl Plugin for UIPlugin {
     fn build(
         &self,
-        app: &App,
+        app: &mut AppBuilder,
     ) {
-        app.add_startup_system(setup);
+        app.init_resource::<inspector::Inspector>()
+            .add_event::<inspector::InspectorEdit>()
+            .add_startup_system(inspector::spawn_inspector.system())
+            .add_system(inspector::inspector_controls.system())
+            .add_system(
+                inspector::apply_inspector_edits
+                    .system()
+                    .label("preparation"),
+            )
+            .add_system(inspector::update_inspector.exclusive_system())
+            .add_system(inspector::dump_entities.exclusive_system())
+            .add_startup_system(panel::spawn_side_panel.system())
+            .add_system(panel::cycle_selection.system())
+            .add_system(panel::update_side_panel.system().after("action"))
+            .init_resource::<minimap::Minimap>()
+            .add_startup_system(minimap::spawn_minimap.system())
+            .add_system(minimap::update_minimap.system().after("action"))
+            .add_system(minimap::minimap_click.system())
+            .init_resource::<log::LogPanel>()
+            .add_startup_system(log::spawn_log_panel.system())
+            .add_system(log::log_controls.system())
+            .add_system(log::update_log_panel.system().after("action"))
+            .init_resource::<console::Console>()
+            .add_startup_system(console::spawn_console.system())
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                console::console_input.system().after(InputSystem),
+            )
+            .add_system(console::run_console_commands.system())
+            .add_system(console::update_console.system());
     }
 }
-
-fn setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-) {
-    // ui camera
-    commands.spawn_bundle(UiCameraBundle::default());
-    // root node
-    commands
-        .spawn_bundle(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                justify_content: JustifyContent::SpaceBetween,
-                ..Default::default()
-            },
-            material: materials.add(Color::NONE.into()),
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            // left vertical fill (border)
-            parent
-                .spawn_bundle(NodeBundle {
-                    style: Style {
-                        size: Size::new(Val::Px(200.0), Val::Percent(100.0)),
-                        border: Rect::all(Val::Px(2.0)),
-                        ..Default::default()
-                    },
-                    material: materials
-                        .add(Color::rgb(0.65, 0.65, 0.65).into()),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    // left vertical fill (content)
-                    parent
-                        .spawn_bundle(NodeBundle {
-                            style: Style {
-                                size: Size::new(
-                                    Val::Percent(100.0),
-                                    Val::Percent(100.0),
-                                ),
-                                align_items: AlignItems::FlexEnd,
-                                ..Default::default()
-                            },
-                            material: materials
-                                .add(Color::rgb(0.15, 0.15, 0.15).into()),
-                            ..Default::default()
-                        })
-                        .with_children(|parent| {
-                            // text
-                            parent.spawn_bundle(TextBundle {
-                                style: Style {
-                                    margin: Rect::all(Val::Px(5.0)),
-                                    ..Default::default()
-                                },
-                                text: Text::with_section(
-                                    "Text Example",
-                                    TextStyle {
-                                        font:      asset_server
-                                            .load("fonts/FiraSans-Bold.ttf"),
-                                        font_size: 30.0,
-                                        color:     Color::WHITE,
-                                    },
-                                    Default::default(),
-                                ),
-                                ..Default::default()
-                            });
-                        });
-                });
-            // right vertical fill
-            parent.spawn_bundle(NodeBundle {
-                style: Style {
-                    size: Size::new(Val::Px(200.0), Val::Percent(100.0)),
-                    ..Default::default()
-                },
-                material: materials.add(Color::rgb(0.15, 0.15, 0.15).into()),
-                ..Default::default()
-            });
-            // absolute positioning
-            parent
-                .spawn_bundle(NodeBundle {
-                    style: Style {
-                        size: Size::new(Val::Px(200.0), Val::Px(200.0)),
-                        position_type: PositionType::Absolute,
-                        position: Rect {
-                            left: Val::Px(210.0),
-                            bottom: Val::Px(10.0),
-                            ..Default::default()
-                        },
-                        border: Rect::all(Val::Px(20.0)),
-                        ..Default::default()
-                    },
-                    material: materials.add(Color::rgb(0.4, 0.4, 1.0).into()),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    parent.spawn_bundle(NodeBundle {
-                        style: Style {
-                            size: Size::new(
-                                Val::Percent(100.0),
-                                Val::Percent(100.0),
-                            ),
-                            ..Default::default()
-                        },
-                        material: materials
-                            .add(Color::rgb(0.8, 0.8, 1.0).into()),
-                        ..Default::default()
-                    });
-                });
-            // render order test: reddest in the back, whitest in the front
-            // (flex center)
-            parent
-                .spawn_bundle(NodeBundle {
-                    style: Style {
-                        size: Size::new(
-                            Val::Percent(100.0),
-                            Val::Percent(100.0),
-                        ),
-                        position_type: PositionType::Absolute,
-                        align_items: AlignItems::Center,
-                        justify_content: JustifyContent::Center,
-                        ..Default::default()
-                    },
-                    material: materials.add(Color::NONE.into()),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    parent
-                        .spawn_bundle(NodeBundle {
-                            style: Style {
-                                size: Size::new(Val::Px(100.0), Val::Px(100.0)),
-                                ..Default::default()
-                            },
-                            material: materials
-                                .add(Color::rgb(1.0, 0.0, 0.0).into()),
-                            ..Default::default()
-                        })
-                        .with_children(|parent| {
-                            parent.spawn_bundle(NodeBundle {
-                                style: Style {
-                                    size: Size::new(
-                                        Val::Px(100.0),
-                                        Val::Px(100.0),
-                                    ),
-                                    position_type: PositionType::Absolute,
-                                    position: Rect {
-                                        left: Val::Px(20.0),
-                                        bottom: Val::Px(20.0),
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                },
-                                material: materials
-                                    .add(Color::rgb(1.0, 0.3, 0.3).into()),
-                                ..Default::default()
-                            });
-                            parent.spawn_bundle(NodeBundle {
-                                style: Style {
-                                    size: Size::new(
-                                        Val::Px(100.0),
-                                        Val::Px(100.0),
-                                    ),
-                                    position_type: PositionType::Absolute,
-                                    position: Rect {
-                                        left: Val::Px(40.0),
-                                        bottom: Val::Px(40.0),
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                },
-                                material: materials
-                                    .add(Color::rgb(1.0, 0.5, 0.5).into()),
-                                ..Default::default()
-                            });
-                            parent.spawn_bundle(NodeBundle {
-                                style: Style {
-                                    size: Size::new(
-                                        Val::Px(100.0),
-                                        Val::Px(100.0),
-                                    ),
-                                    position_type: PositionType::Absolute,
-                                    position: Rect {
-                                        left: Val::Px(60.0),
-                                        bottom: Val::Px(60.0),
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                },
-                                material: materials
-                                    .add(Color::rgb(1.0, 0.7, 0.7).into()),
-                                ..Default::default()
-                            });
-                            // alpha test
-                            parent.spawn_bundle(NodeBundle {
-                                style: Style {
-                                    size: Size::new(
-                                        Val::Px(100.0),
-                                        Val::Px(100.0),
-                                    ),
-                                    position_type: PositionType::Absolute,
-                                    position: Rect {
-                                        left: Val::Px(80.0),
-                                        bottom: Val::Px(80.0),
-                                        ..Default::default()
-                                    },
-                                    ..Default::default()
-                                },
-                                material: materials.add(
-                                    Color::rgba(1.0, 0.9, 0.9, 0.4).into(),
-                                ),
-                                ..Default::default()
-                            });
-                        });
-                });
-            // bevy logo (flex center)
-            parent
-                .spawn_bundle(NodeBundle {
-                    style: Style {
-                        size: Size::new(
-                            Val::Percent(100.0),
-                            Val::Percent(100.0),
-                        ),
-                        position_type: PositionType::Absolute,
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::FlexEnd,
-                        ..Default::default()
-                    },
-                    material: materials.add(Color::NONE.into()),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    // bevy logo (image)
-                    parent.spawn_bundle(ImageBundle {
-                        style: Style {
-                            size: Size::new(Val::Px(500.0), Val::Auto),
-                            ..Default::default()
-                        },
-                        material: materials.add(
-                            asset_server
-                                .load("branding/bevy_logo_dark_big.png")
-                                .into(),
-                        ),
-                        ..Default::default()
-                    });
-                });
-        });
-}
@@ -0,0 +1,191 @@
+// Side panel:
+// Shows the details of the Selected actor on the left of the screen and
+// refreshes them every frame, or those of the clicked tile when no actor is
+// selected. Tab moves the selection to the next actor.
+
+use bevy::prelude::*;
+
+use crate::engine::actor::{Path, Status, Task};
+use crate::engine::render::picking::SelectedTile;
+use crate::engine::world::{Destination, Position, TileEntityMap, TileWeightMap};
+use crate::engine::{Identity, Selected};
+
+const PANEL_WIDTH: f32 = 240.0;
+
+pub struct DetailsText;
+
+pub fn spawn_side_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let style = |font: &str, font_size| TextStyle {
+        font: asset_server.load(font),
+        font_size,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(PANEL_WIDTH), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.0),
+                    top: Val::Px(0.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(10.0)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.1, 0.1, 0.12, 0.8).into()),
+            ..Default::default()
+        })
+        .insert(Interaction::default()) // Keeps clicks from picking behind
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    text: Text {
+                        sections: vec![
+                            TextSection {
+                                value: String::new(),
+                                style: style("fonts/OpenSans-Bold.ttf", 22.0),
+                            },
+                            TextSection {
+                                value: String::new(),
+                                style: style(
+                                    "fonts/OpenSans-Regular.ttf",
+                                    16.0,
+                                ),
+                            },
+                        ],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(DetailsText);
+        });
+}
+
+pub fn describe(
+    position: &Position,
+    destination: Option<&Destination>,
+    task: Option<&Task>,
+    status: Option<&Status>,
+    path: Option<&Path>,
+) -> String {
+    let mut lines = Vec::new();
+    lines.push(format!("Position: ({}, {})", position.x, position.y));
+    lines.push(match destination {
+        Some(Destination(to)) => format!("Destination: ({}, {})", to.x, to.y),
+        None => "Destination: none".to_owned(),
+    });
+    lines.push(match task {
+        Some(task) => {
+            format!("Task: {:?} (priority {})", task.action(), task.priority())
+        }
+        None => "Task: none".to_owned(),
+    });
+    if let Some(status) = status {
+        lines.push(format!("Hunger: {}", status.hunger()));
+        lines.push(format!("Laziness: {}", status.laziness()));
+    }
+    lines.push(match path {
+        Some(path) => format!("Path: {} steps", path.0.len()),
+        None => "Path: none".to_owned(),
+    });
+    lines.join("\n")
+}
+
+pub fn update_side_panel(
+    selected_tile: Res<SelectedTile>,
+    weight_map: Res<TileWeightMap>,
+    entity_map: Res<TileEntityMap>,
+    selected: Query<
+        (
+            Entity,
+            &Identity,
+            &Position,
+            Option<&Destination>,
+            Option<&Task>,
+            Option<&Status>,
+            Option<&Path>,
+        ),
+        With<Selected>,
+    >,
+    mut texts: Query<&mut Text, With<DetailsText>>,
+) {
+    let (title, details) = match selected.iter().next() {
+        Some((entity, identity, position, to, task, status, path)) => (
+            format!("{} ({})\n", identity.name, entity.id()),
+            describe(position, to, task, status, path),
+        ),
+        None => match selected_tile.0 {
+            Some(tile) => (
+                format!("Tile ({}, {})\n", tile.x, tile.y),
+                format!(
+                    "Weight: {}\nOccupants: {}",
+                    weight_map.get(tile.x, tile.y),
+                    entity_map
+                        .occupants(tile.x, tile.y)
+                        .map_or(0, |occupants| occupants.iter().count())
+                ),
+            ),
+            None => (
+                "Nothing selected\n".to_owned(),
+                "Click an actor or press Tab".to_owned(),
+            ),
+        },
+    };
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = title.clone();
+        text.sections[1].value = details.clone();
+    }
+}
+
+pub fn cycle_selection(
+    mut commands: Commands,
+    keyboard: Res<Input<KeyCode>>,
+    actors: Query<Entity, With<Identity>>,
+    selected: Query<Entity, With<Selected>>,
+) {
+    if !keyboard.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    let mut entities: Vec<Entity> = actors.iter().collect();
+    entities.sort();
+    let current = selected.iter().next();
+    let next = match current {
+        Some(current) => entities
+            .iter()
+            .find(|entity| **entity > current)
+            .or_else(|| entities.first()),
+        None => entities.first(),
+    };
+    if let Some(current) = current {
+        commands.entity(current).remove::<Selected>();
+    }
+    if let Some(next) = next {
+        commands.entity(*next).insert(Selected);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_idle_actor() {
+        let details = describe(
+            &Position { x: 3, y: 4 },
+            Some(&Destination(Position { x: 10, y: 0 })),
+            None,
+            None,
+            Some(&Path(vec![Position { x: 4, y: 4 }; 7])),
+        );
+        assert_eq!(
+            details,
+            "Position: (3, 4)\nDestination: (10, 0)\nTask: none\nPath: 7 steps"
+        );
+    }
+}
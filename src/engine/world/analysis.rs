@@ -0,0 +1,286 @@
+// Map analysis:
+// Labels the connected components of walkable tiles in TileWeightMap, using
+// the same eight-way moves the pathfinder allows. The largest component is
+// treated as the main walkable area; spawn points and destinations outside of
+// it can never be reached by most actors, so they are reported rather than
+// left for get_path to fail on silently.
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use bevy::prelude::*;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::engine::world::{Destination, Position, TileWeightMap};
+
+pub struct Connectivity {
+    labels:     Vec<Option<usize>>, // Component of each tile, None if blocked
+    sizes:      Vec<usize>,         // Tile count of each component
+    main:       Option<usize>,      // Largest component
+    main_tiles: Vec<Position>,
+    width:      i64,
+    height:     i64,
+}
+impl Connectivity {
+    pub fn compute(weight_map: &TileWeightMap) -> Self {
+        let width = weight_map.width();
+        let height = weight_map.height();
+        let mut labels = vec![None; (width * height) as usize];
+        let mut sizes = Vec::new();
+        let mut queue = VecDeque::new();
+        for start in 0..labels.len() {
+            let start_position = Position {
+                x: start as i64 % width,
+                y: start as i64 / width,
+            };
+            if labels[start].is_some()
+                || !is_walkable(weight_map, &start_position)
+            {
+                continue;
+            }
+            let label = sizes.len();
+            let mut size = 0;
+            labels[start] = Some(label);
+            queue.push_back(start_position);
+            while let Some(position) = queue.pop_front() {
+                size += 1;
+                for neighbor in position.get_range(1, 1) {
+                    if !is_walkable(weight_map, &neighbor) {
+                        continue;
+                    }
+                    let index = (neighbor.y * width + neighbor.x) as usize;
+                    if labels[index].is_none() {
+                        labels[index] = Some(label);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        // Ties go to the lowest label so the choice is deterministic
+        let main = (0..sizes.len())
+            .max_by_key(|label| (sizes[*label], std::cmp::Reverse(*label)));
+        let main_tiles = labels
+            .iter()
+            .enumerate()
+            .filter(|(_, label)| main.is_some() && **label == main)
+            .map(|(index, _)| Position {
+                x: index as i64 % width,
+                y: index as i64 / width,
+            })
+            .collect();
+        Self {
+            labels,
+            sizes,
+            main,
+            main_tiles,
+            width,
+            height,
+        }
+    }
+    pub fn component_of(
+        &self,
+        position: &Position,
+    ) -> Option<usize> {
+        if 0 <= position.x
+            && position.x < self.width
+            && 0 <= position.y
+            && position.y < self.height
+        {
+            self.labels[(position.y * self.width + position.x) as usize]
+        } else {
+            None
+        }
+    }
+    pub fn is_connected(
+        &self,
+        from: &Position,
+        to: &Position,
+    ) -> bool {
+        match (self.component_of(from), self.component_of(to)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+    pub fn in_main_component(
+        &self,
+        position: &Position,
+    ) -> bool {
+        self.main.is_some() && self.component_of(position) == self.main
+    }
+    pub fn component_count(&self) -> usize { self.sizes.len() }
+    pub fn random_walkable_position(
+        &self,
+        rng: &mut impl Rng,
+    ) -> Option<Position> {
+        // Spawn helper: only returns tiles in the main walkable component
+        self.main_tiles.choose(rng).copied()
+    }
+    pub fn report<'a>(
+        &self,
+        actors: impl Iterator<
+            Item = (Entity, &'a Position, Option<&'a Destination>),
+        >,
+    ) -> ConnectivityReport {
+        let mut report = ConnectivityReport {
+            components:         self.sizes.len(),
+            main_size:          self.main.map_or(0, |label| self.sizes[label]),
+            unreachable_zones:  self
+                .sizes
+                .iter()
+                .enumerate()
+                .filter(|(label, _)| Some(*label) != self.main)
+                .map(|(label, size)| (label, *size))
+                .collect(),
+            blocked_spawns:     Vec::new(),
+            split_destinations: Vec::new(),
+        };
+        for (entity, position, destination) in actors {
+            if self.component_of(position).is_none() {
+                report.blocked_spawns.push((entity, *position));
+            } else if let Some(Destination(destination)) = destination {
+                if !self.is_connected(position, destination) {
+                    report.split_destinations.push((
+                        entity,
+                        *position,
+                        *destination,
+                    ));
+                }
+            }
+        }
+        report
+    }
+}
+
+fn is_walkable(
+    weight_map: &TileWeightMap,
+    position: &Position,
+) -> bool {
+    weight_map.get(position.x, position.y) < i64::MAX
+}
+
+pub struct ConnectivityReport {
+    pub components:         usize,
+    pub main_size:          usize,
+    pub unreachable_zones:  Vec<(usize, usize)>, // (component, tile count)
+    pub blocked_spawns:     Vec<(Entity, Position)>,
+    pub split_destinations: Vec<(Entity, Position, Position)>,
+}
+impl ConnectivityReport {
+    pub fn is_clean(&self) -> bool {
+        self.unreachable_zones.is_empty()
+            && self.blocked_spawns.is_empty()
+            && self.split_destinations.is_empty()
+    }
+}
+impl fmt::Display for ConnectivityReport {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        writeln!(
+            f,
+            "{} walkable component(s), main component has {} tiles",
+            self.components, self.main_size
+        )?;
+        for (label, size) in &self.unreachable_zones {
+            writeln!(f, "\tUnreachable zone #{}: {} tiles", label, size)?;
+        }
+        for (entity, position) in &self.blocked_spawns {
+            writeln!(
+                f,
+                "\t{:?} stands on an obstacle at {:?}",
+                entity, position
+            )?;
+        }
+        for (entity, position, destination) in &self.split_destinations {
+            writeln!(
+                f,
+                "\t{:?} at {:?} cannot reach destination {:?}",
+                entity, position, destination
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub fn update_connectivity(
+    weight_map: Res<TileWeightMap>,
+    mut connectivity: ResMut<Connectivity>,
+    actors: Query<(Entity, &Position, Option<&Destination>)>,
+    new_destinations: Query<
+        (Entity, &Position, &Destination),
+        Added<Destination>,
+    >,
+) {
+    if weight_map.is_changed() {
+        *connectivity = Connectivity::compute(&weight_map);
+        let report = connectivity.report(actors.iter());
+        if report.is_clean() {
+            info!("Map connectivity: {}", report);
+        } else {
+            warn!("Map connectivity: {}", report);
+        }
+    } else {
+        for (entity, position, destination) in new_destinations.iter() {
+            if !connectivity.is_connected(position, &destination.0) {
+                debug!(
+                    "{:?} at {:?} cannot reach destination {:?}",
+                    entity, position, destination.0
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::ground::paint_layer;
+
+    #[test]
+    fn wall_splits_components() {
+        let mut weight_map = TileWeightMap::new(5, 5);
+        for y in 0..5 {
+            weight_map.set(2, y, i64::MAX);
+        }
+        let connectivity = Connectivity::compute(&weight_map);
+        assert_eq!(connectivity.component_count(), 2);
+        assert!(connectivity
+            .is_connected(&Position { x: 0, y: 0 }, &Position { x: 1, y: 4 }));
+        assert!(!connectivity
+            .is_connected(&Position { x: 0, y: 0 }, &Position { x: 4, y: 4 }));
+        assert_eq!(connectivity.component_of(&Position { x: 2, y: 2 }), None);
+    }
+
+    #[test]
+    fn painted_ground_splits_components() {
+        // Grass either side of a wall and of a tile id no ground is drawn with
+        let mut weight_map = TileWeightMap::new(6, 3);
+        let ground = vec![vec![964, 402, 964, 964, 3, 964]; 3];
+        paint_layer(&mut weight_map, &ground, 1);
+        let connectivity = Connectivity::compute(&weight_map);
+        assert_eq!(connectivity.component_count(), 3);
+        assert!(connectivity
+            .is_connected(&Position { x: 2, y: 0 }, &Position { x: 3, y: 2 }));
+        assert!(!connectivity
+            .is_connected(&Position { x: 3, y: 0 }, &Position { x: 5, y: 0 }));
+        assert!(connectivity.in_main_component(&Position { x: 2, y: 1 }));
+        assert_eq!(connectivity.component_of(&Position { x: 4, y: 1 }), None);
+    }
+
+    #[test]
+    fn spawns_only_in_main_component() {
+        let mut weight_map = TileWeightMap::new(6, 3);
+        for y in 0..3 {
+            weight_map.set(1, y, i64::MAX);
+        }
+        let connectivity = Connectivity::compute(&weight_map);
+        let mut rng = rand::thread_rng();
+        for _ in 0..50 {
+            let position =
+                connectivity.random_walkable_position(&mut rng).unwrap();
+            assert!(position.x >= 2);
+        }
+    }
+}
@@ -0,0 +1,189 @@
+// Calendar:
+// Names the days counted by GameTime. Day 0 is the epoch, whose weekday and
+// season are configurable through the Calendar resource; weeks are seven
+// days and every season lasts days_per_season days, at least one.
+
+use std::fmt;
+use std::num::NonZeroU32;
+
+use crate::engine::world::time::GameTime;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+    fn index(self) -> u32 {
+        Weekday::ALL.iter().position(|day| *day == self).unwrap() as u32
+    }
+    pub fn offset(
+        self,
+        days: u32,
+    ) -> Self {
+        Weekday::ALL[((self.index() + days % 7) % 7) as usize]
+    }
+    pub fn is_weekend(self) -> bool {
+        matches!(self, Weekday::Saturday | Weekday::Sunday)
+    }
+}
+impl fmt::Display for Weekday {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+    pub fn offset(
+        self,
+        seasons: u32,
+    ) -> Self {
+        let index = Season::ALL.iter().position(|season| *season == self);
+        Season::ALL[(index.unwrap() + seasons as usize % 4) % 4]
+    }
+}
+impl fmt::Display for Season {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Date {
+    pub day:           u32, // Days since the epoch
+    pub weekday:       Weekday,
+    pub week:          u32, // Weeks since the epoch
+    pub season:        Season,
+    pub day_of_season: u32, // Starting at 1
+}
+impl fmt::Display for Date {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(
+            f,
+            "{}, day {} of {}",
+            self.weekday, self.day_of_season, self.season
+        )
+    }
+}
+
+pub struct Calendar {
+    pub epoch_weekday:   Weekday,
+    pub epoch_season:    Season,
+    pub days_per_season: NonZeroU32, // A season of no days never ends
+}
+impl Default for Calendar {
+    fn default() -> Self {
+        Self {
+            epoch_weekday:   Weekday::Monday,
+            epoch_season:    Season::Spring,
+            days_per_season: NonZeroU32::new(28).unwrap(),
+        }
+    }
+}
+impl Calendar {
+    pub fn date_of_day(
+        &self,
+        day: u32,
+    ) -> Date {
+        let days_per_season = self.days_per_season.get();
+        Date {
+            day,
+            weekday: self.epoch_weekday.offset(day),
+            week: day / 7,
+            season: self.epoch_season.offset(day / days_per_season),
+            day_of_season: day % days_per_season + 1,
+        }
+    }
+    pub fn date(
+        &self,
+        time: GameTime,
+    ) -> Date {
+        self.date_of_day(time.get_stamp().day)
+    }
+    pub fn weekday(
+        &self,
+        time: GameTime,
+    ) -> Weekday {
+        self.date(time).weekday
+    }
+    pub fn season(
+        &self,
+        time: GameTime,
+    ) -> Season {
+        self.date(time).season
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::time::Stamp;
+
+    #[test]
+    fn weekdays_wrap_from_epoch() {
+        let calendar = Calendar {
+            epoch_weekday: Weekday::Friday,
+            ..Default::default()
+        };
+        let time = |day| {
+            GameTime::from_stamp(&Stamp {
+                day,
+                hour: 23,
+                minute: 59,
+                second: 59,
+            })
+        };
+        assert_eq!(calendar.weekday(time(0)), Weekday::Friday);
+        assert_eq!(calendar.weekday(time(2)), Weekday::Sunday);
+        assert_eq!(calendar.weekday(time(3)), Weekday::Monday);
+        assert_eq!(calendar.date(time(15)).week, 2);
+    }
+
+    #[test]
+    fn seasons_follow_configured_length() {
+        let calendar = Calendar {
+            days_per_season: NonZeroU32::new(10).unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(calendar.date_of_day(9).season, Season::Spring);
+        assert_eq!(calendar.date_of_day(10).season, Season::Summer);
+        assert_eq!(calendar.date_of_day(10).day_of_season, 1);
+        assert_eq!(calendar.date_of_day(45).season, Season::Spring);
+    }
+}
@@ -0,0 +1,41 @@
+// Map editing:
+// Changes to the world at runtime are requested by sending TileEdit events.
+// apply_tile_edits writes the new ground type's weight into TileWeightMap and
+// reports every modified position in a single TilesChanged event per frame so
+// that systems holding paths or cached map data can react. The windowed
+// renderer swaps the tile textures itself (render::tilemap).
+
+use bevy::prelude::*;
+
+use crate::engine::world::{GroundType, Position, TileWeightMap};
+
+#[derive(Debug, Copy, Clone)]
+pub struct TileEdit {
+    pub position: Position,
+    pub ground:   GroundType,
+}
+
+#[derive(Debug, Clone)]
+pub struct TilesChanged(pub Vec<Position>);
+
+pub fn apply_tile_edits(
+    mut edits: EventReader<TileEdit>,
+    mut changed: EventWriter<TilesChanged>,
+    mut weight_map: ResMut<TileWeightMap>,
+) {
+    let mut positions = Vec::new();
+    for edit in edits.iter() {
+        let Position { x, y } = edit.position;
+        if !weight_map.contains(x, y) {
+            warn!("Ignoring edit outside of map: {:?}", edit);
+            continue;
+        }
+        weight_map.set(x, y, edit.ground.weight());
+        if !positions.contains(&edit.position) {
+            positions.push(edit.position);
+        }
+    }
+    if !positions.is_empty() {
+        changed.send(TilesChanged(positions));
+    }
+}
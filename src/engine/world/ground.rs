@@ -0,0 +1,112 @@
+// Ground:
+// Reads the ground of the Tiled map named by MapSettings into TileWeightMap
+// while WorldPlugin is built, so the weights are there with or without a
+// window. Layers are read bottom to top, a tile on a higher layer replacing
+// the ground below it, and rows are flipped so that y grows northwards as it
+// does in the tilemap. Tiles of no known GroundType are impassable, as an
+// Obstacle would be, so paths never go through ground nobody has named;
+// empty cells leave the weight alone.
+
+use std::convert::TryFrom;
+use std::path::Path as FilePath;
+
+use bevy::prelude::*;
+
+use crate::engine::world::{GroundType, TileWeightMap};
+
+pub const ASSET_DIR: &str = "assets";
+pub const GROUND_TILESET: &str = "roguelikeCity_magenta";
+
+pub fn paint_layer(
+    weight_map: &mut TileWeightMap,
+    rows: &[Vec<u32>],
+    first_gid: u32,
+) -> usize {
+    // Writes the weights of a layer given as tile ids of GROUND_TILESET, top
+    // row first. Returns how many tiles were of no known GroundType.
+    let mut unknown = 0;
+    for (row, gids) in rows.iter().enumerate() {
+        let y = rows.len() as i64 - 1 - row as i64;
+        for (x, gid) in gids.iter().enumerate() {
+            let x = x as i64;
+            // 0 is an empty cell, showing the layer below
+            if *gid < first_gid || !weight_map.contains(x, y) {
+                continue;
+            }
+            let ground = u16::try_from(gid - first_gid)
+                .ok()
+                .and_then(GroundType::from_texture_index);
+            if ground.is_none() {
+                unknown += 1;
+            }
+            let ground = ground.unwrap_or(GroundType::Obstacle);
+            weight_map.set(x, y, ground.weight());
+        }
+    }
+    unknown
+}
+
+pub fn load_ground(
+    path: &str,
+    weight_map: &mut TileWeightMap,
+) {
+    let file = FilePath::new(ASSET_DIR).join(path);
+    let map = match tiled::parse_file(&file) {
+        Ok(map) => map,
+        Err(error) => {
+            warn!("Could not read the ground of {}: {}", path, error);
+            return;
+        }
+    };
+    let first_gid = match map
+        .tilesets
+        .iter()
+        .find(|tileset| tileset.name == GROUND_TILESET)
+    {
+        Some(tileset) => tileset.first_gid,
+        None => {
+            warn!("{} does not use the {} tiles", path, GROUND_TILESET);
+            return;
+        }
+    };
+    let mut unknown = 0;
+    for layer in map.layers.iter() {
+        if let tiled::LayerData::Finite(tiles) = &layer.tiles {
+            let rows: Vec<Vec<u32>> = tiles
+                .iter()
+                .map(|row| row.iter().map(|tile| tile.gid).collect())
+                .collect();
+            unknown += paint_layer(weight_map, &rows, first_gid);
+        }
+    }
+    if unknown > 0 {
+        warn!(
+            "{} tiles of {} are of no known ground type, taken as obstacles",
+            unknown, path
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_paint_ground_north_up() {
+        // Grass, street and lane markings as in maps/test.tmx, with a
+        // crosswalk on the layer above; the layers are shorter than the map
+        let mut weight_map = TileWeightMap::new(7, 3);
+        let ground = vec![vec![964, 964, 789, 750, 753, 964, 964]; 2];
+        assert_eq!(paint_layer(&mut weight_map, &ground, 1), 0);
+        let crosswalk = vec![vec![0, 0, 827, 827, 827, 0, 0], vec![0; 7]];
+        paint_layer(&mut weight_map, &crosswalk, 1);
+        let weight = |ground: GroundType| ground.weight();
+        assert_eq!(weight_map.get(0, 0), weight(GroundType::ShortGrass));
+        assert_eq!(weight_map.get(2, 0), weight(GroundType::Street));
+        assert_eq!(weight_map.get(3, 0), weight(GroundType::Street));
+        assert_eq!(weight_map.get(3, 1), weight(GroundType::Crosswalk));
+        assert_eq!(weight_map.get(0, 2), 0); // Beyond the layer
+        assert_eq!(paint_layer(&mut weight_map, &[vec![3]], 1), 1);
+        assert_eq!(weight_map.get(0, 0), weight(GroundType::Obstacle));
+    }
+}
@@ -1,18 +1,27 @@
-// Draws the world map and handles pathfinding
+// Holds the world map and handles pathfinding
 //
 // Drawing:
-// Uses bevy_ecs_tilemap to draw tiles on screen.
+// The map is drawn by render::tilemap with bevy_ecs_tilemap, or by the
+// terminal renderer, so WorldPlugin runs without a window. It reads the
+// ground weights from the map file itself (see ground).
 // Note: Support for bevy_ecs_tilemap/tiled_map to be deprecated in future
 //
 
+use std::collections::HashMap;
 use std::ops::Sub;
 
 use bevy::prelude::*;
-use bevy_ecs_tilemap::prelude::*;
 
+pub mod analysis;
+pub mod calendar;
+pub mod edit;
+pub mod ground;
+pub mod scheduler;
+pub mod spatial;
 pub mod time;
+pub mod traffic;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum GroundType {
     ShortGrass,
     TallGrass,
@@ -22,6 +31,53 @@ pub enum GroundType {
     Crosswalk,
     Obstacle,
 }
+impl GroundType {
+    pub const ALL: [GroundType; 7] = [
+        GroundType::ShortGrass,
+        GroundType::TallGrass,
+        GroundType::Sidewalk,
+        GroundType::Path,
+        GroundType::Street,
+        GroundType::Crosswalk,
+        GroundType::Obstacle,
+    ];
+    pub fn weight(self) -> i64 {
+        // Pathing cost of stepping onto this ground; i64::MAX is impassable
+        match self {
+            GroundType::Sidewalk => 1,
+            GroundType::Path => 2,
+            GroundType::ShortGrass => 3,
+            GroundType::Crosswalk => 4,
+            GroundType::TallGrass => 5,
+            GroundType::Street => 10,
+            GroundType::Obstacle => i64::MAX,
+        }
+    }
+    pub fn texture_index(self) -> u16 {
+        // Index into roguelikeCity_magenta.png
+        match self {
+            GroundType::ShortGrass => 963,
+            GroundType::TallGrass => 928,
+            GroundType::Sidewalk => 741,
+            GroundType::Path => 744,
+            GroundType::Street => 788,
+            GroundType::Crosswalk => 827,
+            GroundType::Obstacle => 401,
+        }
+    }
+    pub fn from_texture_index(index: u16) -> Option<Self> {
+        // The lane markings of maps/test.tmx are street as well, and its
+        // crosswalk is the tile before the one drawn for Crosswalk
+        match index {
+            749 | 752 => Some(GroundType::Street),
+            826 => Some(GroundType::Crosswalk),
+            _ => GroundType::ALL
+                .iter()
+                .copied()
+                .find(|ground| ground.texture_index() == index),
+        }
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Hash, Eq)]
 pub struct Position {
@@ -45,6 +101,12 @@ impl Position {
         }
         range
     }
+    pub fn distance_squared(
+        &self,
+        other: &Position,
+    ) -> i64 {
+        (self.x - other.x).pow(2) + (self.y - other.y).pow(2)
+    }
 }
 impl Sub for Position {
     type Output = Self;
@@ -59,6 +121,7 @@ impl Sub for Position {
     }
 }
 
+#[derive(Debug)]
 pub struct Destination(pub Position);
 
 impl PartialEq<Position> for Destination {
@@ -88,6 +151,15 @@ impl TileWeightMap {
         let map = vec![0; (width * height) as usize];
         Self { map, width, height }
     }
+    pub fn width(&self) -> i64 { self.width }
+    pub fn height(&self) -> i64 { self.height }
+    pub fn contains(
+        &self,
+        x: i64,
+        y: i64,
+    ) -> bool {
+        0 <= x && x < self.width && 0 <= y && y < self.height
+    }
     pub fn get(
         &self,
         x: i64,
@@ -115,11 +187,60 @@ impl TileWeightMap {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Occupancy {
+    Blocking,    // People, furniture; at most one per tile
+    NonBlocking, // Items, decals; share a tile with anything
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OccupancyError {
+    OutOfBounds,
+    Blocked(Entity), // Tile already holds this blocking occupant
+    Full,            // Tile holds MAX_NON_BLOCKING non-blocking occupants
+}
+
+const MAX_NON_BLOCKING: usize = 8;
+
+#[derive(Default, Clone)]
+pub struct TileOccupants {
+    pub blocking:     Option<Entity>,
+    pub non_blocking: Vec<Entity>,
+}
+impl TileOccupants {
+    fn admits(
+        &self,
+        occupancy: Occupancy,
+    ) -> Result<(), OccupancyError> {
+        match occupancy {
+            Occupancy::Blocking => match self.blocking {
+                Some(occupant) => Err(OccupancyError::Blocked(occupant)),
+                None => Ok(()),
+            },
+            Occupancy::NonBlocking => {
+                if self.non_blocking.len() < MAX_NON_BLOCKING {
+                    Ok(())
+                } else {
+                    Err(OccupancyError::Full)
+                }
+            }
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        // Blocking occupant first, then items in the order they arrived
+        self.blocking
+            .iter()
+            .chain(self.non_blocking.iter())
+            .copied()
+    }
+}
+
 pub struct TileEntityMap {
-    pub map: Vec<Option<Entity>>, /* Should this be transitioned to fixed
-                                   * size array
-                                   * * because we know the size at compile
-                                   *   time? */
+    pub map: Vec<TileOccupants>, /* Should this be transitioned to fixed
+                                  * size array
+                                  * * because we know the size at compile
+                                  *   time? */
+    placed:  HashMap<Entity, (Position, Occupancy)>,
     width:   i64,
     height:  i64,
 }
@@ -129,32 +250,106 @@ impl TileEntityMap {
         width: i64,
         height: i64,
     ) -> Self {
-        let map = vec![None; (width * height) as usize];
-        Self { map, width, height }
+        let map = vec![TileOccupants::default(); (width * height) as usize];
+        Self {
+            map,
+            placed: HashMap::new(),
+            width,
+            height,
+        }
     }
-    pub fn get(
+    fn index(
         &self,
         x: i64,
         y: i64,
-    ) -> Option<Entity> {
+    ) -> Option<usize> {
         if 0 <= x && x < self.width && 0 <= y && y < self.height {
-            let index = (y * self.width + x) as usize;
-            self.map[index]
+            Some((y * self.width + x) as usize)
         } else {
             None
         }
     }
-    pub fn set(
-        &mut self,
+    pub fn get(
+        &self,
         x: i64,
         y: i64,
-        entity: Option<Entity>,
-    ) {
-        if 0 <= x && x < self.width && 0 <= y && y < self.height {
-            let index = (y * self.width + x) as usize;
-            self.map[index] = entity;
-        } else {
-            panic!("Writing entity to tile outside of map: {:?}, {:?}", x, y)
+    ) -> Option<Entity> {
+        // Returns the blocking occupant, if any
+        self.index(x, y).and_then(|index| self.map[index].blocking)
+    }
+    pub fn occupants(
+        &self,
+        x: i64,
+        y: i64,
+    ) -> Option<&TileOccupants> {
+        self.index(x, y).map(|index| &self.map[index])
+    }
+    pub fn is_blocked(
+        &self,
+        x: i64,
+        y: i64,
+    ) -> bool {
+        self.get(x, y).is_some()
+    }
+    pub fn position_of(
+        &self,
+        entity: Entity,
+    ) -> Option<Position> {
+        self.placed.get(&entity).map(|(position, _)| *position)
+    }
+    pub fn place(
+        &mut self,
+        entity: Entity,
+        position: Position,
+        occupancy: Occupancy,
+    ) -> Result<(), OccupancyError> {
+        // Adds entity to the tile at position, moving it off its previous
+        // tile. The move is all or nothing; on error the entity stays put.
+        let index = self
+            .index(position.x, position.y)
+            .ok_or(OccupancyError::OutOfBounds)?;
+        if self.placed.get(&entity) == Some(&(position, occupancy)) {
+            return Ok(());
+        }
+        self.map[index].admits(occupancy)?;
+        self.remove(entity);
+        match occupancy {
+            Occupancy::Blocking => self.map[index].blocking = Some(entity),
+            Occupancy::NonBlocking => self.map[index].non_blocking.push(entity),
+        }
+        self.placed.insert(entity, (position, occupancy));
+        Ok(())
+    }
+    pub fn remove(
+        &mut self,
+        entity: Entity,
+    ) -> Option<Position> {
+        let (position, occupancy) = self.placed.remove(&entity)?;
+        if let Some(index) = self.index(position.x, position.y) {
+            let tile = &mut self.map[index];
+            match occupancy {
+                Occupancy::Blocking => tile.blocking = None,
+                Occupancy::NonBlocking => {
+                    tile.non_blocking.retain(|other| *other != entity)
+                }
+            }
+        }
+        Some(position)
+    }
+}
+
+pub struct MapSettings {
+    // Tiled map drawn as the ground layer and the size of the simulated grid
+    pub path:   String,
+    pub width:  i64,
+    pub height: i64,
+}
+impl Default for MapSettings {
+    fn default() -> Self {
+        Self {
+            path:   "maps/test.tmx".to_owned(),
+            width:  200,
+            height: 200,
         }
     }
 }
@@ -166,42 +361,57 @@ impl Plugin for WorldPlugin {
         &self,
         app: &mut AppBuilder,
     ) {
-        const WIDTH: i64 = 200;
-        const HEIGHT: i64 = 200;
+        app.init_resource::<MapSettings>();
+        let settings = app.world().get_resource::<MapSettings>().unwrap();
+        let (width, height) = (settings.width, settings.height);
+        let mut weight_map = TileWeightMap::new(width, height);
+        ground::load_ground(&settings.path, &mut weight_map);
         app
             //Tilemap
-            .insert_resource(TileWeightMap::new(WIDTH, HEIGHT))
-            .insert_resource(TileEntityMap::new(WIDTH, HEIGHT))
-            //Window
-            .insert_resource(WindowDescriptor {
-                width: 1270.0,
-                height: 720.0,
-                title: String::from("game"),
-                ..Default::default()
-            })
-            .add_startup_system(init_tilemaps.system())
+            .insert_resource(analysis::Connectivity::compute(&weight_map))
+            .insert_resource(weight_map)
+            .insert_resource(TileEntityMap::new(width, height))
+            .insert_resource(spatial::SpatialIndex::default())
+            .insert_resource(traffic::TrafficMap::new(width, height))
+            .add_system(spatial::index_positions.system().label("preparation"))
+            .add_system(register_occupants.system().label("preparation"))
+            //Editing
+            .add_event::<edit::TileEdit>()
+            .add_event::<edit::TilesChanged>()
+            .add_system(edit::apply_tile_edits.system().label("preparation"))
+            .add_system(
+                analysis::update_connectivity
+                    .system()
+                    .label("planning")
+                    .after("preparation"),
+            )
+            .add_system(
+                traffic::age_traffic
+                    .system()
+                    .label("preparation")
+                    .after("time"),
+            )
             //.add_system(plan_path.system().label("preparation"))
             .add_plugin(time::TimePlugin);
     }
 }
 
-fn init_tilemaps(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+fn register_occupants(
+    mut entity_map: ResMut<TileEntityMap>,
+    added: Query<(Entity, &Position, &Occupancy), Added<Position>>,
+    removed: RemovedComponents<Position>,
 ) {
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-
-    let handle: Handle<TiledMap> = asset_server.load("maps/test.tmx");
-
-    let map_entity = commands.spawn().id();
-
-    commands.entity(map_entity).insert_bundle(TiledMapBundle {
-        tiled_map: handle,
-        map: Map::new(0_u16, map_entity),
-        transform: Transform::from_xyz(0.0, 0.0, 0.0)
-            .mul_transform(Transform::from_scale(Vec3::splat(4.0))),
-        ..Default::default()
-    });
+    for entity in removed.iter() {
+        entity_map.remove(entity);
+    }
+    for (entity, position, occupancy) in added.iter() {
+        if let Err(error) = entity_map.place(entity, *position, *occupancy) {
+            warn!(
+                "Could not place {:?} at {:?}: {:?}",
+                entity, position, error
+            );
+        }
+    }
 }
 
 // pub fn move_weights(
@@ -250,3 +460,41 @@ fn init_tilemaps(
 
 //     weight
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_share_tiles_with_actors() {
+        let mut map = TileEntityMap::new(4, 4);
+        let tile = Position { x: 1, y: 1 };
+        let (actor, pie) = (Entity::new(0), Entity::new(1));
+        map.place(pie, tile, Occupancy::NonBlocking).unwrap();
+        map.place(actor, tile, Occupancy::Blocking).unwrap();
+        assert_eq!(map.get(1, 1), Some(actor));
+        let occupants: Vec<Entity> =
+            map.occupants(1, 1).unwrap().iter().collect();
+        assert_eq!(occupants, vec![actor, pie]);
+    }
+
+    #[test]
+    fn blocked_move_keeps_previous_tile() {
+        let mut map = TileEntityMap::new(4, 4);
+        let (first, second) = (Entity::new(0), Entity::new(1));
+        map.place(first, Position { x: 0, y: 0 }, Occupancy::Blocking)
+            .unwrap();
+        map.place(second, Position { x: 1, y: 0 }, Occupancy::Blocking)
+            .unwrap();
+        assert_eq!(
+            map.place(second, Position { x: 0, y: 0 }, Occupancy::Blocking),
+            Err(OccupancyError::Blocked(first))
+        );
+        assert_eq!(map.get(1, 0), Some(second));
+        assert_eq!(map.remove(first), Some(Position { x: 0, y: 0 }));
+        map.place(second, Position { x: 0, y: 0 }, Occupancy::Blocking)
+            .unwrap();
+        assert_eq!(map.get(1, 0), None);
+        assert_eq!(map.position_of(second), Some(Position { x: 0, y: 0 }));
+    }
+}
@@ -0,0 +1,264 @@
+// Scheduler:
+// A single priority queue of future events keyed on GameTime, in place of
+// per-system timers such as the animal processes' old AnimalTimer or the
+// GameTime each actor used to carry for its next step; actors now step on
+// their own recurring "actor_step" event. Systems register one-shot or
+// recurring events by name, optionally for a target entity; each frame, after
+// the clock advances, every due event is sent as a ScheduledEvent. Events fire
+// in order of their time and, for equal times, in the order they were
+// registered, so dispatch is deterministic.
+//
+// Consumers read EventReader<ScheduledEvent> and match on the name, e.g.
+// `scheduler.every(opening, GameDuration::from_minutes(15), "bus_arrives",
+// None)`.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+
+use bevy::prelude::*;
+
+use crate::engine::world::time::{GameDuration, GameTime};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScheduleId(u64);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ScheduledEvent {
+    pub id:     ScheduleId,
+    pub name:   &'static str,
+    pub target: Option<Entity>,
+    pub time:   GameTime, // When the event was due, not when it was sent
+}
+
+struct Entry {
+    time:     GameTime,
+    sequence: u64, // Registration order, breaks ties between equal times
+    id:       ScheduleId,
+    name:     &'static str,
+    target:   Option<Entity>,
+    period:   Option<GameDuration>,
+}
+impl Ord for Entry {
+    fn cmp(
+        &self,
+        other: &Self,
+    ) -> Ordering {
+        (self.time, self.sequence).cmp(&(other.time, other.sequence))
+    }
+}
+impl PartialOrd for Entry {
+    fn partial_cmp(
+        &self,
+        other: &Self,
+    ) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl PartialEq for Entry {
+    fn eq(
+        &self,
+        other: &Self,
+    ) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Entry {}
+
+#[derive(Default)]
+pub struct Scheduler {
+    queue:         BinaryHeap<Reverse<Entry>>,
+    cancelled:     HashSet<ScheduleId>,
+    next_id:       u64,
+    next_sequence: u64,
+}
+impl Scheduler {
+    fn push(
+        &mut self,
+        time: GameTime,
+        id: ScheduleId,
+        name: &'static str,
+        target: Option<Entity>,
+        period: Option<GameDuration>,
+    ) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.queue.push(Reverse(Entry {
+            time,
+            sequence,
+            id,
+            name,
+            target,
+            period,
+        }));
+    }
+    fn new_id(&mut self) -> ScheduleId {
+        self.next_id += 1;
+        ScheduleId(self.next_id)
+    }
+    pub fn once(
+        &mut self,
+        time: GameTime,
+        name: &'static str,
+        target: Option<Entity>,
+    ) -> ScheduleId {
+        let id = self.new_id();
+        self.push(time, id, name, target, None);
+        id
+    }
+    pub fn every(
+        &mut self,
+        first: GameTime,
+        period: GameDuration,
+        name: &'static str,
+        target: Option<Entity>,
+    ) -> ScheduleId {
+        assert!(
+            period > GameDuration::ZERO,
+            "Recurring event {:?} needs a non-zero period",
+            name
+        );
+        let id = self.new_id();
+        self.push(first, id, name, target, Some(period));
+        id
+    }
+    pub fn cancel(
+        &mut self,
+        id: ScheduleId,
+    ) -> bool {
+        // Entries are dropped lazily when they reach the front of the queue.
+        // False if nothing is queued under id, having fired or been cancelled.
+        let queued = self.queue.iter().any(|Reverse(entry)| entry.id == id);
+        queued && self.cancelled.insert(id)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.queue
+            .iter()
+            .all(|Reverse(entry)| self.cancelled.contains(&entry.id))
+    }
+    pub fn next_due(&self) -> Option<GameTime> {
+        self.queue
+            .iter()
+            .filter(|Reverse(entry)| !self.cancelled.contains(&entry.id))
+            .map(|Reverse(entry)| entry.time)
+            .min()
+    }
+    pub fn pop_due(
+        &mut self,
+        now: GameTime,
+    ) -> Vec<ScheduledEvent> {
+        // Recurring events that fell behind fire once per missed period
+        let mut due = Vec::new();
+        while let Some(Reverse(entry)) = self.queue.peek() {
+            if entry.time > now {
+                break;
+            }
+            let Reverse(entry) = self.queue.pop().unwrap();
+            // Each id has at most one entry queued, so it can be forgotten
+            if self.cancelled.remove(&entry.id) {
+                continue;
+            }
+            due.push(ScheduledEvent {
+                id:     entry.id,
+                name:   entry.name,
+                target: entry.target,
+                time:   entry.time,
+            });
+            if let Some(period) = entry.period {
+                self.push(
+                    entry.time + period,
+                    entry.id,
+                    entry.name,
+                    entry.target,
+                    entry.period,
+                );
+            }
+        }
+        due
+    }
+}
+
+pub fn dispatch_scheduled(
+    game_time: Res<GameTime>,
+    mut scheduler: ResMut<Scheduler>,
+    mut events: EventWriter<ScheduledEvent>,
+) {
+    for event in scheduler.pop_due(*game_time) {
+        events.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::time::Stamp;
+
+    fn at(
+        hour: u32,
+        minute: u32,
+    ) -> GameTime {
+        GameTime::from_stamp(&Stamp {
+            day: 0,
+            hour,
+            minute,
+            second: 0,
+        })
+    }
+
+    #[test]
+    fn fires_in_time_then_registration_order() {
+        let mut scheduler = Scheduler::default();
+        scheduler.once(at(9, 0), "late", None);
+        scheduler.once(at(8, 0), "shop_opens", None);
+        scheduler.once(at(8, 0), "lights_off", None);
+        assert!(scheduler.pop_due(at(7, 59)).is_empty());
+        let names: Vec<_> = scheduler
+            .pop_due(at(8, 30))
+            .iter()
+            .map(|event| event.name)
+            .collect();
+        assert_eq!(names, vec!["shop_opens", "lights_off"]);
+        assert_eq!(scheduler.next_due(), Some(at(9, 0)));
+    }
+
+    #[test]
+    fn targets_take_turns_each_period() {
+        let mut scheduler = Scheduler::default();
+        let (first, second) = (Entity::new(1), Entity::new(2));
+        let step = GameDuration::from_seconds(1);
+        scheduler.every(at(6, 0), step, "actor_step", Some(first));
+        scheduler.every(at(6, 0), step, "actor_step", Some(second));
+        let targets = |events: Vec<ScheduledEvent>| -> Vec<_> {
+            events.iter().filter_map(|event| event.target).collect()
+        };
+        assert_eq!(targets(scheduler.pop_due(at(6, 0))), vec![first, second]);
+        assert!(scheduler.pop_due(at(6, 0)).is_empty());
+        let next = at(6, 0) + step;
+        assert_eq!(targets(scheduler.pop_due(next)), vec![first, second]);
+    }
+
+    #[test]
+    fn recurring_events_catch_up_and_cancel() {
+        let mut scheduler = Scheduler::default();
+        let bus = scheduler.every(
+            at(8, 0),
+            GameDuration::from_minutes(15),
+            "bus_arrives",
+            None,
+        );
+        let times: Vec<_> = scheduler
+            .pop_due(at(8, 40))
+            .iter()
+            .map(|event| event.time)
+            .collect();
+        assert_eq!(times, vec![at(8, 0), at(8, 15), at(8, 30)]);
+        assert_eq!(scheduler.next_due(), Some(at(8, 45)));
+        assert!(scheduler.cancel(bus));
+        assert!(!scheduler.cancel(bus));
+        assert!(scheduler.pop_due(at(12, 0)).is_empty());
+        assert!(scheduler.is_empty());
+        let once = scheduler.once(at(13, 0), "shop_closes", None);
+        scheduler.pop_due(at(13, 0));
+        assert!(!scheduler.cancel(once));
+        assert!(scheduler.cancelled.is_empty());
+    }
+}
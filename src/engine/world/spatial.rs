@@ -0,0 +1,247 @@
+// Spatial index:
+// Buckets entity positions into square cells so that proximity queries only
+// visit the cells overlapping the query area instead of scanning every entity.
+// Unlike TileEntityMap, any number of entities can share a tile. The index is
+// filled by index_positions when a Position is added, kept current by
+// move_actor, and pruned when a Position is removed.
+//
+// Queries take a filter closure so callers can restrict results to entities
+// with a given component, e.g. `|e| animals.get(e).is_ok()`.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::engine::world::Position;
+
+const DEFAULT_CELL_SIZE: i64 = 8;
+
+pub struct SpatialIndex {
+    cells:     HashMap<(i64, i64), Vec<(Entity, Position)>>,
+    positions: HashMap<Entity, Position>,
+    cell_size: i64,
+}
+impl Default for SpatialIndex {
+    fn default() -> Self { Self::new(DEFAULT_CELL_SIZE) }
+}
+impl SpatialIndex {
+    pub fn new(cell_size: i64) -> Self {
+        assert!(cell_size > 0, "Spatial index cell size must be positive.");
+        Self {
+            cells: HashMap::new(),
+            positions: HashMap::new(),
+            cell_size,
+        }
+    }
+    fn cell_of(
+        &self,
+        position: &Position,
+    ) -> (i64, i64) {
+        (
+            position.x.div_euclid(self.cell_size),
+            position.y.div_euclid(self.cell_size),
+        )
+    }
+    pub fn len(&self) -> usize { self.positions.len() }
+    pub fn is_empty(&self) -> bool { self.positions.is_empty() }
+    pub fn get(
+        &self,
+        entity: Entity,
+    ) -> Option<Position> {
+        self.positions.get(&entity).copied()
+    }
+    pub fn insert(
+        &mut self,
+        entity: Entity,
+        position: Position,
+    ) {
+        // Inserting an entity that is already indexed moves it
+        self.remove(entity);
+        let cell = self.cell_of(&position);
+        self.cells.entry(cell).or_default().push((entity, position));
+        self.positions.insert(entity, position);
+    }
+    pub fn remove(
+        &mut self,
+        entity: Entity,
+    ) -> Option<Position> {
+        let position = self.positions.remove(&entity)?;
+        let cell = self.cell_of(&position);
+        if let Some(bucket) = self.cells.get_mut(&cell) {
+            bucket.retain(|(other, _)| *other != entity);
+            if bucket.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+        Some(position)
+    }
+    pub fn within_rect(
+        &self,
+        min: &Position,
+        max: &Position,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Vec<(Entity, Position)> {
+        // Inclusive on both corners
+        let (min_cx, min_cy) = self.cell_of(min);
+        let (max_cx, max_cy) = self.cell_of(max);
+        let mut found = Vec::new();
+        for cx in min_cx..=max_cx {
+            for cy in min_cy..=max_cy {
+                if let Some(bucket) = self.cells.get(&(cx, cy)) {
+                    for (entity, position) in bucket {
+                        if min.x <= position.x
+                            && position.x <= max.x
+                            && min.y <= position.y
+                            && position.y <= max.y
+                            && filter(*entity)
+                        {
+                            found.push((*entity, *position));
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+    pub fn within_radius(
+        &self,
+        center: &Position,
+        radius: i64,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Vec<(Entity, Position)> {
+        // Euclidean radius, measured in tiles
+        let min = Position {
+            x: center.x - radius,
+            y: center.y - radius,
+        };
+        let max = Position {
+            x: center.x + radius,
+            y: center.y + radius,
+        };
+        self.within_rect(&min, &max, filter)
+            .into_iter()
+            .filter(|(_, position)| {
+                center.distance_squared(position) <= radius * radius
+            })
+            .collect()
+    }
+    pub fn nearest(
+        &self,
+        center: &Position,
+        count: usize,
+        filter: impl Fn(Entity) -> bool,
+    ) -> Vec<(Entity, Position)> {
+        // Searches outward one ring of cells at a time, stopping once the
+        // k-th candidate is closer than anything the next ring could hold.
+        let mut found: Vec<(i64, Entity, Position)> = Vec::new();
+        if count == 0 {
+            return Vec::new();
+        }
+        let (center_cx, center_cy) = self.cell_of(center);
+        let max_ring = self
+            .cells
+            .keys()
+            .map(|(cx, cy)| (cx - center_cx).abs().max((cy - center_cy).abs()))
+            .max()
+            .unwrap_or(0);
+        for ring in 0..=max_ring {
+            if found.len() >= count {
+                let inner_edge = (ring - 1) * self.cell_size;
+                if found[count - 1].0 <= inner_edge * inner_edge {
+                    break;
+                }
+            }
+            for cx in (center_cx - ring)..=(center_cx + ring) {
+                for cy in (center_cy - ring)..=(center_cy + ring) {
+                    let on_ring = (cx - center_cx).abs() == ring
+                        || (cy - center_cy).abs() == ring;
+                    if !on_ring {
+                        continue;
+                    }
+                    if let Some(bucket) = self.cells.get(&(cx, cy)) {
+                        for (entity, position) in bucket {
+                            if filter(*entity) {
+                                found.push((
+                                    center.distance_squared(position),
+                                    *entity,
+                                    *position,
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            // Ties are broken by entity so results are deterministic
+            found.sort_by_key(|(distance, entity, _)| (*distance, *entity));
+        }
+        found.truncate(count);
+        found
+            .into_iter()
+            .map(|(_, entity, position)| (entity, position))
+            .collect()
+    }
+}
+
+pub fn index_positions(
+    mut spatial_index: ResMut<SpatialIndex>,
+    added: Query<(Entity, &Position), Added<Position>>,
+    removed: RemovedComponents<Position>,
+) {
+    for entity in removed.iter() {
+        spatial_index.remove(entity);
+    }
+    for (entity, position) in added.iter() {
+        spatial_index.insert(entity, *position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexed(positions: &[(i64, i64)]) -> SpatialIndex {
+        let mut index = SpatialIndex::new(4);
+        for (id, (x, y)) in positions.iter().enumerate() {
+            index.insert(Entity::new(id as u32), Position { x: *x, y: *y });
+        }
+        index
+    }
+
+    #[test]
+    fn radius_query_spans_cells() {
+        let index = indexed(&[(0, 0), (3, 4), (5, 5), (-3, 0), (20, 20)]);
+        let mut found: Vec<u32> = index
+            .within_radius(&Position { x: 0, y: 0 }, 5, |_| true)
+            .iter()
+            .map(|(entity, _)| entity.id())
+            .collect();
+        found.sort_unstable();
+        assert_eq!(found, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn nearest_is_ordered_and_filtered() {
+        let index = indexed(&[(10, 10), (1, 1), (-2, 0), (40, 40), (0, 3)]);
+        let nearest: Vec<u32> = index
+            .nearest(&Position { x: 0, y: 0 }, 3, |entity| entity.id() != 2)
+            .iter()
+            .map(|(entity, _)| entity.id())
+            .collect();
+        assert_eq!(nearest, vec![1, 4, 0]);
+    }
+
+    #[test]
+    fn insert_moves_existing_entity() {
+        let mut index = indexed(&[(0, 0)]);
+        index.insert(Entity::new(0), Position { x: 30, y: 30 });
+        assert_eq!(index.len(), 1);
+        assert!(index
+            .within_rect(
+                &Position { x: -1, y: -1 },
+                &Position { x: 1, y: 1 },
+                |_| true
+            )
+            .is_empty());
+        assert_eq!(index.get(Entity::new(0)), Some(Position { x: 30, y: 30 }));
+    }
+}
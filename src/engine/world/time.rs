@@ -1,11 +1,19 @@
 // TODO: Implement fixed timestep so systems can catch up to render
 
 use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
 use std::time::Duration;
 
 use bevy::{core::Stopwatch, prelude::*};
 
-#[derive(Debug)]
+use crate::engine::world::{calendar::Calendar,
+                           scheduler::{dispatch_scheduled, ScheduledEvent,
+                                       Scheduler}};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Stamp {
     pub day:    u32,
     pub hour:   u32,
@@ -13,68 +21,108 @@ pub struct Stamp {
     pub second: u32,
 }
 
-#[derive(Copy, Clone, Eq)]
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 3600;
+const SECONDS_PER_DAY: u64 = 86400;
+
+#[derive(Debug, Copy, Clone, Eq)]
 pub struct GameTime {
-    raw: u32, // 0 = 12AM day 0; 108000 = 6AM day 1
+    raw: u64, // 0 = 12AM day 0; 108000 = 6AM day 1
 }
 
 impl GameTime {
     pub fn from_stamp(time: &Stamp) -> Self {
         let mut raw = 0;
-        raw += time.day * 86400;
-        raw += time.hour * 3600;
-        raw += time.minute * 60;
-        raw += time.second;
+        raw += u64::from(time.day) * SECONDS_PER_DAY;
+        raw += u64::from(time.hour) * SECONDS_PER_HOUR;
+        raw += u64::from(time.minute) * SECONDS_PER_MINUTE;
+        raw += u64::from(time.second);
         Self { raw }
     }
     pub fn tick(
         &mut self,
-        seconds: u32,
+        duration: GameDuration,
     ) {
-        self.raw += seconds;
+        *self = *self + duration;
+    }
+    pub fn checked_add(
+        self,
+        duration: GameDuration,
+    ) -> Option<Self> {
+        self.raw
+            .checked_add(duration.seconds)
+            .map(|raw| Self { raw })
+    }
+    pub fn checked_sub(
+        self,
+        duration: GameDuration,
+    ) -> Option<Self> {
+        self.raw
+            .checked_sub(duration.seconds)
+            .map(|raw| Self { raw })
+    }
+    pub fn checked_duration_since(
+        self,
+        earlier: Self,
+    ) -> Option<GameDuration> {
+        self.raw
+            .checked_sub(earlier.raw)
+            .map(GameDuration::from_seconds)
+    }
+    pub fn saturating_duration_since(
+        self,
+        earlier: Self,
+    ) -> GameDuration {
+        // Zero if earlier is actually later
+        self.checked_duration_since(earlier)
+            .unwrap_or(GameDuration::ZERO)
     }
 
     // Analog Clock Functions (floor output for digital):
-    fn get_day(self) -> f32 { self.raw as f32 / 86400.0 }
-    fn get_hour(self) -> f32 {
+    pub fn get_day(self) -> f32 { self.raw as f32 / 86400.0 }
+    pub fn get_hour(self) -> f32 {
         // hours since new day
         (self.raw % 86400) as f32 / 3600.0
     }
-    fn get_minute(self) -> f32 {
+    pub fn get_minute(self) -> f32 {
         // minutes since new hour
         (self.raw % 3600) as f32 / 60.0
     }
-    fn get_second(self) -> f32 {
+    pub fn get_second(self) -> f32 {
         // seconds since new minute
         (self.raw % 60) as f32
     }
     pub fn get_stamp(self) -> Stamp {
+        // Integer arithmetic; f32 loses whole seconds after ~190 days. Days
+        // past u32::MAX, some eleven million years, saturate.
+        let day = self.raw / SECONDS_PER_DAY;
         Stamp {
-            day:    self.get_day().floor() as u32,
-            hour:   self.get_hour().floor() as u32,
-            minute: self.get_minute().floor() as u32,
-            second: self.get_second().floor() as u32,
+            day:    u32::try_from(day).unwrap_or(u32::MAX),
+            hour:   (self.raw % SECONDS_PER_DAY / SECONDS_PER_HOUR) as u32,
+            minute: (self.raw % SECONDS_PER_HOUR / SECONDS_PER_MINUTE) as u32,
+            second: (self.raw % SECONDS_PER_MINUTE) as u32,
         }
     }
+    pub fn time_of_day(self) -> GameDuration {
+        GameDuration::from_seconds(self.raw % SECONDS_PER_DAY)
+    }
     // Helpers
     #[allow(non_snake_case)]
     pub fn is_AM(self) -> bool { self.get_hour() < 12.0 }
     pub fn how_soon(
         self,
         other: Self,
-    ) -> u32 {
-        let seconds = other.raw.checked_sub(self.raw);
-        seconds.unwrap_or(0)
+    ) -> Option<GameDuration> {
+        // None if other has already passed
+        other.checked_duration_since(self)
     }
     pub fn copy_and_tick(
         self,
-        seconds: u32,
+        duration: GameDuration,
     ) -> Self {
         // Produces a new time instance at a later point for scheduling relative
         // to self
-        Self {
-            raw: self.raw + seconds,
-        }
+        self + duration
     }
 }
 impl Ord for GameTime {
@@ -102,8 +150,220 @@ impl PartialEq for GameTime {
         self.raw == other.raw
     }
 }
+impl Add<GameDuration> for GameTime {
+    type Output = Self;
+    fn add(
+        self,
+        duration: GameDuration,
+    ) -> Self {
+        self.checked_add(duration).expect("GameTime overflowed")
+    }
+}
+impl AddAssign<GameDuration> for GameTime {
+    fn add_assign(
+        &mut self,
+        duration: GameDuration,
+    ) {
+        *self = *self + duration;
+    }
+}
+impl Sub<GameDuration> for GameTime {
+    type Output = Self;
+    fn sub(
+        self,
+        duration: GameDuration,
+    ) -> Self {
+        self.checked_sub(duration)
+            .expect("GameTime went before 12AM day 0")
+    }
+}
+impl fmt::Display for GameTime {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let stamp = self.get_stamp();
+        write!(
+            f,
+            "Day {} {:02}:{:02}:{:02}",
+            stamp.day, stamp.hour, stamp.minute, stamp.second
+        )
+    }
+}
 
-pub struct GameTimeRate(f32);
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GameDuration {
+    seconds: u64,
+}
+impl GameDuration {
+    pub const ZERO: Self = Self { seconds: 0 };
+
+    pub const fn from_seconds(seconds: u64) -> Self { Self { seconds } }
+    pub const fn from_minutes(minutes: u64) -> Self {
+        Self::from_seconds(minutes * SECONDS_PER_MINUTE)
+    }
+    pub const fn from_hours(hours: u64) -> Self {
+        Self::from_seconds(hours * SECONDS_PER_HOUR)
+    }
+    pub const fn from_days(days: u64) -> Self {
+        Self::from_seconds(days * SECONDS_PER_DAY)
+    }
+    pub fn as_seconds(self) -> u64 { self.seconds }
+    pub fn checked_sub(
+        self,
+        other: Self,
+    ) -> Option<Self> {
+        self.seconds
+            .checked_sub(other.seconds)
+            .map(Self::from_seconds)
+    }
+}
+impl Add for GameDuration {
+    type Output = Self;
+    fn add(
+        self,
+        other: Self,
+    ) -> Self {
+        Self::from_seconds(
+            self.seconds
+                .checked_add(other.seconds)
+                .expect("GameDuration overflowed"),
+        )
+    }
+}
+impl Sub for GameDuration {
+    type Output = Self;
+    fn sub(
+        self,
+        other: Self,
+    ) -> Self {
+        self.checked_sub(other)
+            .expect("GameDuration went below zero")
+    }
+}
+impl fmt::Display for GameDuration {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        // Largest units first, zero units omitted: "2d 3h", "45m 10s", "0s"
+        let parts = [
+            (self.seconds / SECONDS_PER_DAY, "d"),
+            (self.seconds % SECONDS_PER_DAY / SECONDS_PER_HOUR, "h"),
+            (self.seconds % SECONDS_PER_HOUR / SECONDS_PER_MINUTE, "m"),
+            (self.seconds % SECONDS_PER_MINUTE, "s"),
+        ];
+        let mut written = false;
+        for (amount, unit) in parts.iter() {
+            if *amount > 0 {
+                if written {
+                    write!(f, " ")?;
+                }
+                write!(f, "{}{}", amount, unit)?;
+                written = true;
+            }
+        }
+        if !written {
+            write!(f, "0s")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDurationError(String);
+impl fmt::Display for ParseDurationError {
+    fn fmt(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        write!(f, "Invalid duration: {:?}", self.0)
+    }
+}
+impl std::error::Error for ParseDurationError {}
+
+impl FromStr for GameDuration {
+    type Err = ParseDurationError;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        // Accepts clock notation, "06:30" or "06:30:15", or units, "2d 3h"
+        let error = || ParseDurationError(text.to_owned());
+        let text = text.trim();
+        if text.contains(':') {
+            let fields: Vec<&str> = text.split(':').collect();
+            if fields.len() < 2 || fields.len() > 3 {
+                return Err(error());
+            }
+            let mut seconds: u64 = 0;
+            for (field, unit) in fields
+                .iter()
+                .zip([SECONDS_PER_HOUR, SECONDS_PER_MINUTE, 1].iter())
+            {
+                let value: u64 = field.parse().map_err(|_| error())?;
+                if *unit != SECONDS_PER_HOUR && value >= 60 {
+                    return Err(error());
+                }
+                seconds = value
+                    .checked_mul(*unit)
+                    .and_then(|part| seconds.checked_add(part))
+                    .ok_or_else(error)?;
+            }
+            return Ok(Self::from_seconds(seconds));
+        }
+        let mut seconds: u64 = 0;
+        let mut any = false;
+        for part in text.split_whitespace() {
+            let split =
+                part.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+            let (amount, unit) = part.split_at(split);
+            let amount: u64 = amount.parse().map_err(|_| error())?;
+            let unit = match unit {
+                "d" => SECONDS_PER_DAY,
+                "h" => SECONDS_PER_HOUR,
+                "m" => SECONDS_PER_MINUTE,
+                "s" => 1,
+                _ => return Err(error()),
+            };
+            seconds = amount
+                .checked_mul(unit)
+                .and_then(|part| seconds.checked_add(part))
+                .ok_or_else(error)?;
+            any = true;
+        }
+        if any {
+            Ok(Self::from_seconds(seconds))
+        } else {
+            Err(error())
+        }
+    }
+}
+
+pub struct GameTimeRate {
+    rate:   f32, // Game seconds per real second
+    paused: bool,
+}
+impl GameTimeRate {
+    pub fn new(rate: f32) -> Self {
+        Self {
+            rate,
+            paused: false,
+        }
+    }
+    pub fn rate(&self) -> f32 { self.rate }
+    pub fn set_rate(
+        &mut self,
+        rate: f32,
+    ) {
+        assert!(rate > 0.0, "Game time rate must be positive, use pause");
+        self.rate = rate;
+    }
+    pub fn is_paused(&self) -> bool { self.paused }
+    pub fn toggle_pause(&mut self) { self.paused = !self.paused; }
+}
+
+// Game time elapsed since GameTime's last whole second, in [0, 1), for
+// rendering that moves more smoothly than the simulation
+#[derive(Default)]
+pub struct SubSecond(pub f32);
 
 struct GameInWatch(Stopwatch);
 
@@ -112,14 +372,20 @@ fn advance_time(
     realtime: Res<Time>,
     mut realtimer: ResMut<GameInWatch>,
     rate: Res<GameTimeRate>,
+    mut sub_second: ResMut<SubSecond>,
 ) {
+    if rate.is_paused() {
+        return;
+    }
     realtimer.0.tick(realtime.delta());
 
-    let step = realtimer.0.elapsed().mul_f32(rate.0);
+    let step = realtimer.0.elapsed().mul_f32(rate.rate);
     let seconds = step.as_secs();
-    localtime.tick(seconds as u32);
+    localtime.tick(GameDuration::from_seconds(seconds));
 
-    let remainder = (step - Duration::new(seconds, 0)).div_f32(rate.0);
+    let remainder = step - Duration::new(seconds, 0);
+    sub_second.0 = remainder.as_secs_f32();
+    let remainder = remainder.div_f32(rate.rate);
     realtimer.0.set_elapsed(remainder);
 }
 
@@ -135,8 +401,107 @@ impl Plugin for TimePlugin {
             minute: 0,
             second: 0,
         }))
+        .init_resource::<Calendar>()
         .insert_resource(GameInWatch(Stopwatch::new()))
-        .insert_resource(GameTimeRate(1.5))
-        .add_system(advance_time.system().label("preparation"));
+        .insert_resource(GameTimeRate::new(1.5))
+        .init_resource::<SubSecond>()
+        .init_resource::<Scheduler>()
+        .add_event::<ScheduledEvent>()
+        .add_system(advance_time.system().label("preparation").label("time"))
+        .add_system(
+            dispatch_scheduled
+                .system()
+                .label("preparation")
+                .after("time"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn parses_clock_and_unit_notation() {
+        let parse = |text: &str| text.parse::<GameDuration>();
+        assert_eq!(parse("06:30"), Ok(GameDuration::from_minutes(390)));
+        assert_eq!(parse("00:00:15"), Ok(GameDuration::from_seconds(15)));
+        assert_eq!(
+            parse("2d 3h"),
+            Ok(GameDuration::from_days(2) + GameDuration::from_hours(3))
+        );
+        assert!(parse("06:75").is_err());
+        assert!(parse("3 days").is_err());
+        assert!(parse("").is_err());
+        assert!(parse("18446744073709551615:00").is_err());
+        assert_eq!(GameDuration::from_minutes(390).to_string(), "6h 30m");
+    }
+
+    #[test]
+    #[should_panic(expected = "GameTime went before 12AM day 0")]
+    fn subtracting_past_the_epoch_panics() {
+        let _ = GameTime { raw: 10 } - GameDuration::from_seconds(11);
+    }
+
+    #[test]
+    fn far_days_saturate() {
+        assert_eq!(GameTime { raw: u64::MAX }.get_stamp().day, u32::MAX);
+    }
+
+    #[test]
+    fn time_arithmetic_is_checked() {
+        let six = GameTime::from_stamp(&Stamp {
+            day:    0,
+            hour:   6,
+            minute: 0,
+            second: 0,
+        });
+        let seven = six + GameDuration::from_hours(1);
+        assert_eq!(
+            seven.checked_duration_since(six),
+            Some(GameDuration::from_hours(1))
+        );
+        assert_eq!(six.checked_duration_since(seven), None);
+        assert_eq!(six.saturating_duration_since(seven), GameDuration::ZERO);
+        assert_eq!(seven - GameDuration::from_hours(1), six);
+        assert_eq!(six.how_soon(seven), Some(GameDuration::from_hours(1)));
+        assert_eq!(seven.how_soon(six), None);
+        assert_eq!(six.checked_sub(GameDuration::from_days(3)), None);
+    }
+
+    proptest! {
+        #[test]
+        fn stamp_round_trip(raw in 0..u64::from(u32::MAX) * SECONDS_PER_DAY) {
+            let time = GameTime { raw };
+            prop_assert_eq!(GameTime::from_stamp(&time.get_stamp()), time);
+        }
+
+        #[test]
+        fn stamp_fields_in_range(
+            day in any::<u32>(),
+            hour in 0..24_u32,
+            minute in 0..60_u32,
+            second in 0..60_u32,
+        ) {
+            let stamp = Stamp { day, hour, minute, second };
+            prop_assert_eq!(GameTime::from_stamp(&stamp).get_stamp(), stamp);
+        }
+
+        #[test]
+        fn duration_format_round_trip(seconds in 0..u64::MAX / 2) {
+            let duration = GameDuration::from_seconds(seconds);
+            prop_assert_eq!(duration.to_string().parse(), Ok(duration));
+        }
+
+        #[test]
+        fn add_then_subtract(raw in 0..u64::MAX / 2, seconds in 0..u64::MAX / 2) {
+            let time = GameTime { raw };
+            let duration = GameDuration::from_seconds(seconds);
+            let later = time + duration;
+            prop_assert_eq!(later.checked_duration_since(time), Some(duration));
+            prop_assert_eq!(later - duration, time);
+        }
     }
 }
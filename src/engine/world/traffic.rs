@@ -0,0 +1,168 @@
+// Traffic:
+// Counts the actor steps onto every tile over the last game hour, in one
+// bucket per game minute; a bucket is cleared when its minute comes round
+// again. Level designers read it through the heatmap overlay or export it,
+// both exports putting the top row of the map (highest y) first.
+
+use std::path::Path as FilePath;
+
+use bevy::prelude::*;
+
+use crate::engine::world::{time::GameTime, Position};
+
+const BUCKETS: usize = 60; // Game minutes kept
+
+pub struct TrafficMap {
+    counts: Vec<u16>, // BUCKETS consecutive grids, indexed by minute % BUCKETS
+    minute: u64,      // Minutes since the epoch of the newest bucket
+    width:  i64,
+    height: i64,
+}
+impl TrafficMap {
+    pub fn new(
+        width: i64,
+        height: i64,
+    ) -> Self {
+        Self {
+            counts: vec![0; BUCKETS * (width * height) as usize],
+            minute: 0,
+            width,
+            height,
+        }
+    }
+    pub fn width(&self) -> i64 { self.width }
+    pub fn height(&self) -> i64 { self.height }
+    fn tiles(&self) -> usize { (self.width * self.height) as usize }
+    pub fn update(
+        &mut self,
+        now: GameTime,
+    ) {
+        // Clears the buckets of every minute that passed since the last call
+        let stamp = now.get_stamp();
+        let minute = u64::from(stamp.day) * 1440
+            + u64::from(stamp.hour) * 60
+            + u64::from(stamp.minute);
+        if minute <= self.minute {
+            return;
+        }
+        let tiles = self.tiles();
+        let stale = (minute - self.minute).min(BUCKETS as u64);
+        for passed in (minute - stale + 1)..=minute {
+            let bucket = (passed % BUCKETS as u64) as usize;
+            for count in &mut self.counts[bucket * tiles..(bucket + 1) * tiles]
+            {
+                *count = 0;
+            }
+        }
+        self.minute = minute;
+    }
+    pub fn record(
+        &mut self,
+        position: &Position,
+        now: GameTime,
+    ) {
+        if !(0 <= position.x
+            && position.x < self.width
+            && 0 <= position.y
+            && position.y < self.height)
+        {
+            return;
+        }
+        self.update(now);
+        let bucket = (self.minute % BUCKETS as u64) as usize;
+        let index = bucket * self.tiles()
+            + (position.y * self.width + position.x) as usize;
+        self.counts[index] = self.counts[index].saturating_add(1);
+    }
+    pub fn count(
+        &self,
+        x: i64,
+        y: i64,
+    ) -> u32 {
+        let tile = (y * self.width + x) as usize;
+        (0..BUCKETS)
+            .map(|bucket| u32::from(self.counts[bucket * self.tiles() + tile]))
+            .sum()
+    }
+    pub fn grid(&self) -> Vec<u32> {
+        // Steps per tile over the last hour, row by row from y = 0
+        let mut grid = vec![0; self.tiles()];
+        for bucket in self.counts.chunks(self.tiles()) {
+            for (total, count) in grid.iter_mut().zip(bucket) {
+                *total += u32::from(*count);
+            }
+        }
+        grid
+    }
+    pub fn to_csv(&self) -> String {
+        let grid = self.grid();
+        let mut csv = String::new();
+        for y in (0..self.height).rev() {
+            let row = &grid[(y * self.width) as usize..][..self.width as usize];
+            let cells: Vec<String> =
+                row.iter().map(|count| count.to_string()).collect();
+            csv.push_str(&cells.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+    pub fn save_png(
+        &self,
+        path: &FilePath,
+    ) -> image::ImageResult<()> {
+        // Greyscale, white is the busiest tile
+        let grid = self.grid();
+        let max = grid.iter().copied().max().unwrap_or(0).max(1);
+        let image = image::GrayImage::from_fn(
+            self.width as u32,
+            self.height as u32,
+            |x, row| {
+                let y = self.height as u32 - 1 - row;
+                let count = grid[(y * self.width as u32 + x) as usize];
+                image::Luma([(count * 255 / max) as u8])
+            },
+        );
+        image.save(path)
+    }
+}
+
+pub fn age_traffic(
+    game_time: Res<GameTime>,
+    mut traffic: ResMut<TrafficMap>,
+) {
+    traffic.update(*game_time);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::world::time::Stamp;
+
+    fn at(
+        hour: u32,
+        minute: u32,
+    ) -> GameTime {
+        GameTime::from_stamp(&Stamp {
+            day: 0,
+            hour,
+            minute,
+            second: 0,
+        })
+    }
+
+    #[test]
+    fn steps_expire_after_an_hour() {
+        let mut traffic = TrafficMap::new(3, 2);
+        let crossing = Position { x: 1, y: 0 };
+        traffic.record(&crossing, at(8, 0));
+        traffic.record(&crossing, at(8, 30));
+        traffic.record(&Position { x: 0, y: 1 }, at(8, 30));
+        traffic.record(&Position { x: 5, y: 5 }, at(8, 30)); // Off the map
+        assert_eq!(traffic.count(1, 0), 2);
+        assert_eq!(traffic.to_csv(), "1,0,0\n0,2,0\n");
+        traffic.update(at(9, 0));
+        assert_eq!(traffic.count(1, 0), 1);
+        traffic.update(at(12, 0));
+        assert_eq!(traffic.grid(), vec![0; 6]);
+    }
+}
@@ -1,88 +1,150 @@
-use std::ops::RangeInclusive;
+use std::time::Duration;
 
-use bevy::{diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
-           ecs::{archetype::Archetypes, component::Components,
-                 entity::Entities},
+use bevy::{app::ScheduleRunnerSettings,
+           diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
            prelude::*};
 use bevy_ecs_tilemap::prelude::*;
 use pretty_trace::*;
-use rand::Rng;
 
 mod engine;
 
 fn main() {
     PrettyTrace::new().on();
-    App::build()
+    let mut app = App::build();
+    if std::env::args().any(|arg| arg == "--iso") {
+        app.insert_resource(engine::world::MapSettings {
+            path:   "maps/isotest.tmx".to_owned(),
+            width:  100,
+            height: 100,
+        })
+        .insert_resource(engine::render::Projection::IsoStaggered {
+            tile_size: Vec2::new(64.0, 64.0),
+        });
+    }
+    if std::env::args().any(|arg| arg == "--tui") {
+        // The same simulation drawn in the terminal, without a window
+        app.insert_resource(ScheduleRunnerSettings::run_loop(
+            Duration::from_secs_f64(1.0 / 30.0),
+        ))
+        .add_plugins(MinimalPlugins)
+        .add_plugin(engine::tui::TerminalPlugin);
+    } else {
+        app.insert_resource(WindowDescriptor {
+            width: 1270.0,
+            height: 720.0,
+            title: String::from("game"),
+            ..Default::default()
+        })
         .insert_resource(ClearColor(Color::rgb(0.0, 0.0, 0.05)))
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(LogDiagnosticsPlugin::default())
         .add_plugins(DefaultPlugins)
         .add_plugin(engine::render::GraphicsPlugin)
-        .add_plugin(engine::actor::ActorPlugin)
-        .add_plugin(engine::world::WorldPlugin)
+        .add_plugin(engine::ui::clock::ClockPlugin)
+        .add_plugin(engine::ui::UIPlugin)
         .add_plugin(TilemapPlugin)
         .add_plugin(TiledMapPlugin)
+        .add_startup_system(add_street_lamps.system());
+    }
+    app.add_plugin(engine::actor::ActorPlugin)
+        .add_plugin(engine::world::WorldPlugin)
         .add_startup_system(add_people.system())
-        .add_system(inspect.system())
+        .add_startup_system(add_player.system())
         .add_system(new_destination.system())
         .run();
 }
 
+fn npc_sprite(
+    position: engine::world::Position,
+    asset_server: &Option<Res<AssetServer>>,
+    texture_atlases: &mut Option<ResMut<Assets<TextureAtlas>>>,
+    sprite_sheets: &mut Option<ResMut<engine::render::animation::SpriteSheets>>,
+    projection: &Option<Res<engine::render::Projection>>,
+) -> SpriteSheetBundle {
+    match (asset_server, texture_atlases, sprite_sheets, projection) {
+        (
+            Some(asset_server),
+            Some(texture_atlases),
+            Some(sprite_sheets),
+            Some(projection),
+        ) => engine::render::init_sprite_sheet(
+            engine::render::animation::NPC_SHEET,
+            asset_server,
+            texture_atlases,
+            sprite_sheets,
+            position,
+            projection,
+        ),
+        // Nothing to load without a window; the terminal draws glyphs
+        _ => SpriteSheetBundle::default(),
+    }
+}
+
 fn add_people(
     mut commands: Commands,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    asset_server: Res<AssetServer>,
+    mut texture_atlases: Option<ResMut<Assets<TextureAtlas>>>,
+    mut sprite_sheets: Option<ResMut<engine::render::animation::SpriteSheets>>,
+    asset_server: Option<Res<AssetServer>>,
+    connectivity: Res<engine::world::analysis::Connectivity>,
+    settings: Res<engine::world::MapSettings>,
+    projection: Option<Res<engine::render::Projection>>,
 ) {
     let mut x = 0;
+    let park = connectivity
+        .random_walkable_position(&mut rand::thread_rng())
+        .expect("Map has no walkable tiles");
 
     while x < 50 {
-        let x_range = RangeInclusive::new(0, 199);
-        let y_range = x_range.clone();
         let mut rng = rand::thread_rng();
-        let position = engine::world::Position {
-            x: rng.gen_range(x_range),
-            y: rng.gen_range(y_range),
+        let position = connectivity
+            .random_walkable_position(&mut rng)
+            .expect("Map has no walkable tiles");
+        let work = engine::world::Position {
+            x: settings.width - 1,
+            y: settings.height - 1,
         };
-        let destination = engine::world::Destination(engine::world::Position {
-            x: 199,
-            y: 199,
-        });
+        let destination = engine::world::Destination(work);
 
-        let sprite_sheet = engine::render::init_sprite_sheet(
-            &"sprites/NPC1 (2).png".to_owned(),
+        let sprite_sheet = npc_sprite(
+            position,
             &asset_server,
             &mut texture_atlases,
-            position,
+            &mut sprite_sheets,
+            &projection,
         );
-        engine::spawn_actor(
+        let actor = engine::spawn_actor(
             &mut commands,
             engine::Identity {
                 specific: true,
                 name:     "Grumph Torgi".to_owned(),
             },
             position,
-            destination,
+            Some(destination),
             sprite_sheet,
         );
+        // Works weekdays where it is headed first, wanders the rest of the time
+        commands
+            .entity(actor)
+            .insert(engine::actor::Intelligent)
+            .insert(engine::actor::Status::new(20))
+            .insert(weekly_schedule(work, park));
         x += 1;
     }
     while x < 100 {
-        let x_range = RangeInclusive::new(0, 199);
-        let y_range = x_range.clone();
         let mut rng = rand::thread_rng();
-        let position = engine::world::Position {
-            x: rng.gen_range(x_range),
-            y: rng.gen_range(y_range),
-        };
+        let position = connectivity
+            .random_walkable_position(&mut rng)
+            .expect("Map has no walkable tiles");
 
         let destination =
             engine::world::Destination(engine::world::Position { x: 0, y: 0 });
 
-        let sprite_sheet = engine::render::init_sprite_sheet(
-            &"sprites/NPC1 (2).png".to_owned(),
+        let sprite_sheet = npc_sprite(
+            position,
             &asset_server,
             &mut texture_atlases,
-            position,
+            &mut sprite_sheets,
+            &projection,
         );
         engine::spawn_actor(
             &mut commands,
@@ -91,13 +153,89 @@ fn add_people(
                 name:     "Grumph Torgi".to_owned(),
             },
             position,
-            destination,
+            Some(destination),
             sprite_sheet,
         );
         x += 1;
     }
 }
 
+fn weekly_schedule(
+    work: engine::world::Position,
+    park: engine::world::Position,
+) -> engine::actor::schedule::RecurringSchedule {
+    use engine::actor::{schedule::{DaySelector, ScheduleRule},
+                        Action, ActionParameters, Task};
+    use engine::world::calendar::Weekday;
+    engine::actor::schedule::RecurringSchedule(vec![
+        ScheduleRule {
+            days:   DaySelector::Weekdays,
+            hour:   9,
+            minute: 0,
+            task:   Task::new(Action::GoTo, ActionParameters::at(work), 50),
+        },
+        ScheduleRule {
+            days:   DaySelector::On(vec![Weekday::Sunday]),
+            hour:   12,
+            minute: 0,
+            task:   Task::new(Action::GoTo, ActionParameters::at(park), 30),
+        },
+    ])
+}
+
+fn add_player(
+    mut commands: Commands,
+    mut texture_atlases: Option<ResMut<Assets<TextureAtlas>>>,
+    mut sprite_sheets: Option<ResMut<engine::render::animation::SpriteSheets>>,
+    asset_server: Option<Res<AssetServer>>,
+    connectivity: Res<engine::world::analysis::Connectivity>,
+    projection: Option<Res<engine::render::Projection>>,
+) {
+    let mut rng = rand::thread_rng();
+    let position = connectivity
+        .random_walkable_position(&mut rng)
+        .expect("Map has no walkable tiles");
+    let sprite_sheet = npc_sprite(
+        position,
+        &asset_server,
+        &mut texture_atlases,
+        &mut sprite_sheets,
+        &projection,
+    );
+    // Stands still until told where to go
+    let player = engine::spawn_actor(
+        &mut commands,
+        engine::Identity {
+            specific: true,
+            name:     "Player".to_owned(),
+        },
+        position,
+        None,
+        sprite_sheet,
+    );
+    commands
+        .entity(player)
+        .insert(engine::actor::player::Player);
+}
+
+fn add_street_lamps(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    weight_map: Res<engine::world::TileWeightMap>,
+    projection: Res<engine::render::Projection>,
+) {
+    // Along the kerbs, a dozen tiles apart
+    for position in engine::render::lighting::kerbside_lamps(&weight_map, 12) {
+        engine::render::lighting::spawn_light_source(
+            &mut commands,
+            &mut materials,
+            &projection,
+            position,
+            engine::render::lighting::LightSource::default(),
+        );
+    }
+}
+
 // This should be moved to engine::actor module
 fn new_destination(
     mut commands: Commands,
@@ -106,42 +244,17 @@ fn new_destination(
         (
             With<engine::world::Position>,
             Without<engine::world::Destination>,
+            Without<engine::actor::player::Player>,
         ),
     >,
+    connectivity: Res<engine::world::analysis::Connectivity>,
 ) {
     for entity in query.iter() {
-        let x_range = RangeInclusive::new(0, 199);
-        let y_range = x_range.clone();
         let mut rng = rand::thread_rng();
-        let x = rng.gen_range(x_range);
-        let y = rng.gen_range(y_range);
-        let destination =
-            engine::world::Destination(engine::world::Position { x, y });
-        commands.entity(entity).insert(destination);
-    }
-}
-
-fn inspect(
-    keyboard: Res<Input<KeyCode>>,
-    all_entities: Query<Entity>,
-    entities: &Entities,
-    archetypes: &Archetypes,
-    components: &Components,
-) {
-    if keyboard.just_pressed(KeyCode::F1) {
-        for entity in all_entities.iter() {
-            println!("Entity: {:?}", entity);
-            if let Some(entity_location) = entities.get(entity) {
-                if let Some(archetype) =
-                    archetypes.get(entity_location.archetype_id)
-                {
-                    for component in archetype.components() {
-                        if let Some(info) = components.get_info(component) {
-                            println!("\tComponent: {}", info.name());
-                        }
-                    }
-                }
-            }
+        if let Some(position) = connectivity.random_walkable_position(&mut rng)
+        {
+            let destination = engine::world::Destination(position);
+            commands.entity(entity).insert(destination);
         }
     }
 }